@@ -0,0 +1,79 @@
+//! Generates the pruning tables at build time for the `embedded-tables` feature.
+//!
+//! The generation code is the library's own, included module by module at the root
+//! so their `crate::` paths resolve, and the embedded tables are exactly what
+//! `PruningTables::generate` produces at runtime.
+
+// The modules are private here, which unused items and a few naming lints complain about
+#![allow(dead_code, clippy::upper_case_acronyms, clippy::wrong_self_convention)]
+
+#[cfg(feature = "embedded-tables")]
+#[path = "src/coordinate.rs"]
+mod coordinate;
+#[cfg(feature = "embedded-tables")]
+#[path = "src/cubie_cube.rs"]
+mod cubie_cube;
+#[cfg(feature = "embedded-tables")]
+#[path = "src/generation.rs"]
+mod generation;
+#[cfg(feature = "embedded-tables")]
+#[path = "src/pruning_table.rs"]
+mod pruning_table;
+#[cfg(feature = "embedded-tables")]
+#[path = "src/rng.rs"]
+mod rng;
+#[cfg(feature = "embedded-tables")]
+#[path = "src/symmetry.rs"]
+mod symmetry;
+#[cfg(feature = "embedded-tables")]
+#[path = "src/table_cache.rs"]
+mod table_cache;
+#[cfg(feature = "embedded-tables")]
+#[path = "src/table_stats.rs"]
+mod table_stats;
+#[cfg(feature = "embedded-tables")]
+#[path = "src/turn.rs"]
+mod turn;
+
+/// Set to "mod3" to embed 2-bit tables instead of the default 4-bit ones.
+const ENCODING_ENV: &str = "RUBIKS_CUBE_EMBEDDED_ENCODING";
+
+fn main() {
+    for module in [
+        "coordinate",
+        "cubie_cube",
+        "generation",
+        "pruning_table",
+        "rng",
+        "symmetry",
+        "table_cache",
+        "table_stats",
+        "turn",
+    ] {
+        println!("cargo:rerun-if-changed=src/{}.rs", module);
+    }
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-env-changed={}", ENCODING_ENV);
+
+    #[cfg(feature = "embedded-tables")]
+    embed_tables();
+}
+
+#[cfg(feature = "embedded-tables")]
+fn embed_tables() {
+    use generation::GenerationConfig;
+    use pruning_table::{ PruningEncoding, PruningTables };
+
+    let encoding = match std::env::var(ENCODING_ENV).as_deref() {
+        Ok("mod3") => PruningEncoding::Mod3,
+        Ok("nibble") | Err(_) => PruningEncoding::Nibble,
+        Ok(other) => panic!("{} must be \"nibble\" or \"mod3\", not {:?}", ENCODING_ENV, other),
+    };
+
+    let tables = PruningTables::generate_with(&GenerationConfig::new().encoding(encoding));
+    let bytes = rkyv::to_bytes::<rkyv::rancor::Error>(&tables).expect("Failed to serialize tables");
+
+    let out_dir = std::env::var_os("OUT_DIR").expect("OUT_DIR is set by cargo");
+    let path = std::path::Path::new(&out_dir).join("pruning_tables.rkyv");
+    std::fs::write(&path, &bytes).expect("Failed to write embedded tables");
+}
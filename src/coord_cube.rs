@@ -0,0 +1,92 @@
+use crate::cubie_cube::CubieCube;
+use crate::pruning_table::TableLookup;
+use crate::table_stats::VerifyError;
+
+/// A cube described only by its coordinates.
+///
+/// Moves are applied with move table lookups instead of `CubieCube::multiply`.
+/// The Phase 2 coordinates are only meaningful once the cube is in the G1 subgroup
+/// (no twist, no flip, slice edges in the slice).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CoordCube {
+    // Phase 1
+    pub twist: u16, // 0..2186
+    pub flip: u16, // 0..2047
+    pub slice: u16, // 0..494
+
+    // Phase 2
+    pub corner: u16, // 0..40319
+    pub ud_edges: u16, // 0..40319
+    pub slice_perm: u16, // 0..23
+}
+
+impl CoordCube {
+    pub fn from_cubie(cube: &CubieCube) -> Self {
+        Self {
+            twist: cube.get_twist(),
+            flip: cube.get_flip(),
+            slice: cube.get_slice_sorted(),
+            corner: cube.get_corner_perm() as u16,
+            ud_edges: cube.get_ud_edges() as u16,
+            slice_perm: cube.get_slice_perm() as u16,
+        }
+    }
+
+    /// Applies move `m` (index into `Turn::ALL`) to the Phase 1 coordinates.
+    #[inline(always)]
+    pub fn phase1_move<T: TableLookup + ?Sized>(&self, tables: &T, m: usize) -> Self {
+        Self {
+            twist: tables.twist_move(self.twist as usize, m),
+            flip: tables.flip_move(self.flip as usize, m),
+            slice: tables.slice_move(self.slice as usize, m),
+            ..*self
+        }
+    }
+
+    /// Applies a G1 move `m` (index into `Turn::ALL`) to the Phase 2 coordinates.
+    #[inline(always)]
+    pub fn phase2_move<T: TableLookup + ?Sized>(&self, tables: &T, m: usize) -> Self {
+        Self {
+            corner: tables.corner_move(self.corner as usize, m),
+            ud_edges: tables.ud_edge_move(self.ud_edges as usize, m),
+            slice_perm: tables.slice_perm_move(self.slice_perm as usize, m),
+            ..*self
+        }
+    }
+
+    /// Exact number of moves needed to reach G1.
+    #[inline(always)]
+    pub fn phase1_depth<T: TableLookup + ?Sized>(&self, tables: &T) -> Result<u8, VerifyError> {
+        tables.phase1_depth(self.twist as usize, self.flip as usize, self.slice as usize)
+    }
+
+    /// `phase1_depth` for a cube one move away from a cube at distance `parent`.
+    /// This is the cheap way to follow the distance during search.
+    #[inline(always)]
+    pub fn phase1_depth_from<T: TableLookup + ?Sized>(&self, tables: &T, parent: u8) -> Result<u8, VerifyError> {
+        tables.phase1_depth_from(parent, self.twist as usize, self.flip as usize, self.slice as usize)
+    }
+
+    /// Lower bound on the number of G1 moves needed to solve the cube.
+    #[inline(always)]
+    pub fn phase2_depth<T: TableLookup + ?Sized>(&self, tables: &T) -> Result<u8, VerifyError> {
+        tables.phase2_depth(self.corner as usize, self.ud_edges as usize, self.slice_perm as usize)
+    }
+
+    /// Distances in the corner-slice and edge-slice Phase 2 tables.
+    /// The larger one is `phase2_depth`.
+    #[inline(always)]
+    pub fn phase2_depths<T: TableLookup + ?Sized>(&self, tables: &T) -> Result<[u8; 2], VerifyError> {
+        tables.phase2_depths(self.corner as usize, self.ud_edges as usize, self.slice_perm as usize)
+    }
+
+    /// `phase2_depths` for a cube one G1 move away from a cube at distances `parent`.
+    #[inline(always)]
+    pub fn phase2_depths_from<T: TableLookup + ?Sized>(&self, tables: &T, parent: [u8; 2]) -> Result<[u8; 2], VerifyError> {
+        tables.phase2_depths_from(parent, self.corner as usize, self.ud_edges as usize, self.slice_perm as usize)
+    }
+
+    pub fn is_phase2_solved(&self) -> bool {
+        self.corner == 0 && self.ud_edges == 0 && self.slice_perm == 0
+    }
+}
@@ -0,0 +1,128 @@
+use crate::cubie_cube::CubieCube;
+
+/// A number describing part of a cube's state, the building block of move and pruning tables.
+///
+/// Implement it for your own piece subsets to build tables for custom subgoals with
+/// `MoveTable::for_coordinate` and `pair_pruning_table`.
+pub trait Coordinate {
+    /// Number of values, the coordinate is always in `0..COUNT`.
+    const COUNT: usize;
+
+    /// The coordinate of `cube`.
+    fn get(cube: &CubieCube) -> usize;
+
+    /// Any cube with coordinate `value`. Applying a move to it and reading the coordinate
+    /// back must give the same result for every such cube.
+    fn set(value: usize) -> CubieCube;
+}
+
+/// Orientation of the corners (0..2186).
+pub struct Twist;
+
+/// Orientation of the edges (0..2047).
+pub struct Flip;
+
+/// Positions of the four UD-slice edges, ignoring their order (0..494).
+pub struct SliceSorted;
+
+/// Permutation of the corners (0..40319).
+pub struct CornerPerm;
+
+/// Permutation of the eight U and D edges, only meaningful in G1 (0..40319).
+pub struct UdEdges;
+
+/// Permutation of the four UD-slice edges within the slice, only meaningful in G1 (0..23).
+pub struct SlicePerm;
+
+impl Coordinate for Twist {
+    const COUNT: usize = 2187;
+
+    fn get(cube: &CubieCube) -> usize {
+        cube.get_twist() as usize
+    }
+
+    fn set(value: usize) -> CubieCube {
+        CubieCube::set_twist(value as u16)
+    }
+}
+
+impl Coordinate for Flip {
+    const COUNT: usize = 2048;
+
+    fn get(cube: &CubieCube) -> usize {
+        cube.get_flip() as usize
+    }
+
+    fn set(value: usize) -> CubieCube {
+        CubieCube::set_flip(value as u16)
+    }
+}
+
+impl Coordinate for SliceSorted {
+    const COUNT: usize = 495;
+
+    fn get(cube: &CubieCube) -> usize {
+        cube.get_slice_sorted() as usize
+    }
+
+    fn set(value: usize) -> CubieCube {
+        CubieCube::set_slice_sorted(value as u16)
+    }
+}
+
+impl Coordinate for CornerPerm {
+    const COUNT: usize = 40320;
+
+    fn get(cube: &CubieCube) -> usize {
+        cube.get_corner_perm()
+    }
+
+    fn set(value: usize) -> CubieCube {
+        CubieCube::set_corner_perm(value)
+    }
+}
+
+impl Coordinate for UdEdges {
+    const COUNT: usize = 40320;
+
+    fn get(cube: &CubieCube) -> usize {
+        cube.get_ud_edges()
+    }
+
+    fn set(value: usize) -> CubieCube {
+        CubieCube::set_ud_edges(value)
+    }
+}
+
+impl Coordinate for SlicePerm {
+    const COUNT: usize = 24;
+
+    fn get(cube: &CubieCube) -> usize {
+        cube.get_slice_perm()
+    }
+
+    fn set(value: usize) -> CubieCube {
+        CubieCube::set_slice_perm(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_round_trip<C: Coordinate>() {
+        for value in 0..C::COUNT {
+            assert_eq!(C::get(&C::set(value)), value, "{} does not round trip", std::any::type_name::<C>());
+        }
+    }
+
+    #[test]
+    fn test_coordinates_round_trip() {
+        assert_round_trip::<Twist>();
+        assert_round_trip::<Flip>();
+        assert_round_trip::<SliceSorted>();
+        assert_round_trip::<CornerPerm>();
+        assert_round_trip::<UdEdges>();
+        assert_round_trip::<SlicePerm>();
+    }
+}
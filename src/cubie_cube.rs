@@ -1,3 +1,4 @@
+use rand::{ Rng, RngExt };
 use rand::seq::IndexedRandom;
 
 // Precomputed Binomial Coefficients (n choose k) for the Slice coordinate
@@ -19,6 +20,11 @@ const C_NK: [[u16; 5]; 12] = [
 // Precomputed Factorials (0! to 7!)
 const FACTORIALS_7: [usize; 8] = [1, 1, 2, 6, 24, 120, 720, 5040];
 
+// Precomputed Factorials (0! to 11!) for the full edge permutation
+const FACTORIALS_11: [usize; 12] = [
+    1, 1, 2, 6, 24, 120, 720, 5040, 40320, 362880, 3628800, 39916800,
+];
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Corner {
     URF,
@@ -31,6 +37,19 @@ pub enum Corner {
     DRB,
 }
 
+impl Corner {
+    pub const ALL: [Corner; 8] = [
+        Corner::URF,
+        Corner::UFL,
+        Corner::ULB,
+        Corner::UBR,
+        Corner::DFR,
+        Corner::DLF,
+        Corner::DBL,
+        Corner::DRB,
+    ];
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Edge {
     UR,
@@ -47,6 +66,23 @@ pub enum Edge {
     BR,
 }
 
+impl Edge {
+    pub const ALL: [Edge; 12] = [
+        Edge::UR,
+        Edge::UF,
+        Edge::UL,
+        Edge::UB,
+        Edge::DR,
+        Edge::DF,
+        Edge::DL,
+        Edge::DB,
+        Edge::FR,
+        Edge::FL,
+        Edge::BL,
+        Edge::BR,
+    ];
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct CubieCube {
     // Permutation of the 8 corners (0..7)
@@ -72,14 +108,13 @@ impl CubieCube {
         Self::SOLVED
     }
 
-    /// Generates a fully scrambled cube using 30 random valid moves.
-    pub fn scramble(&mut self, moves: usize) -> Vec<crate::turn::Turn> {
-        let mut rng = rand::rng();
+    /// Scrambles the cube with `moves` random valid moves drawn from `rng`.
+    pub fn scramble<R: Rng + ?Sized>(&mut self, moves: usize, rng: &mut R) -> Vec<crate::turn::Turn> {
         let mut last_move: Option<crate::turn::Turn> = None;
         let mut history = Vec::new();
 
         while history.len() < moves {
-            let candidate = *crate::turn::Turn::ALL.choose(&mut rng).unwrap();
+            let candidate = *crate::turn::Turn::ALL.choose(rng).unwrap();
 
             if crate::turn::is_move_allowed(candidate, last_move) {
                 *self = self.multiply(&candidate.to_cubie());
@@ -90,6 +125,33 @@ impl CubieCube {
         history
     }
 
+    /// Samples a cube uniformly from all 43,252,003,274,489,856,000 reachable states.
+    /// Each coordinate is drawn independently, then two edges are swapped if the
+    /// corner and edge parities disagree. Each valid state is hit exactly twice, so the result is uniform.
+    pub fn random_state<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        let twist = CubieCube::set_twist(rng.random_range(0..2187));
+        let flip = CubieCube::set_flip(rng.random_range(0..2048));
+        let corners = CubieCube::set_corner_perm(rng.random_range(0..40320));
+        let edges = CubieCube::set_edge_perm(rng.random_range(0..479001600));
+
+        let mut cc = CubieCube {
+            cp: corners.cp,
+            co: twist.co,
+            ep: edges.ep,
+            eo: flip.eo,
+        };
+        if cc.corner_parity() != cc.edge_parity() {
+            cc.ep.swap(10, 11);
+        }
+        cc
+    }
+
+    pub fn apply_moves(&mut self, moves: &[crate::turn::Turn]) {
+        for m in moves {
+            *self = self.multiply(&m.to_cubie());
+        }
+    }
+
     pub fn apply_sequence(&mut self, sequence: &str) -> Result<(), String> {
         for move_str in sequence.split_whitespace() {
             let m: crate::turn::Turn = move_str.parse()?;
@@ -147,6 +209,12 @@ impl CubieCube {
     };
 }
 
+impl Default for CubieCube {
+    fn default() -> Self {
+        Self::SOLVED
+    }
+}
+
 impl CubieCube {
     /// Group multiplication: Returns a new cube representing "self * other".
     /// This applies the transformation 'other' to 'self'.
@@ -161,7 +229,11 @@ impl CubieCube {
             // Apply orientation (sum modulo 3)
             let ori_a = self.co[other.cp[i] as usize];
             let ori_b = other.co[i];
-            result.co[i] = (ori_a + ori_b) % 3;
+            result.co[i] = if ori_a < 3 && ori_b < 3 {
+                (ori_a + ori_b) % 3
+            } else {
+                mirrored_orientation(ori_a, ori_b)
+            };
         }
 
         // Handle Edges
@@ -186,8 +258,8 @@ impl CubieCube {
             let item = self.cp[i] as usize;
             result.cp[item] = i as u8;
             let ori = self.co[i];
-            // Inverse orientation is (3 - ori) % 3
-            result.co[item] = (3 - ori) % 3;
+            // Inverse orientation is (3 - ori) % 3, mirrored corners are their own inverse
+            result.co[item] = if ori < 3 { (3 - ori) % 3 } else { ori };
         }
 
         for i in 0..12 {
@@ -199,6 +271,44 @@ impl CubieCube {
 
         result
     }
+
+    /// Parity of the corner permutation (0 = even, 1 = odd)
+    pub fn corner_parity(&self) -> u8 {
+        permutation_parity(&self.cp)
+    }
+
+    /// Parity of the edge permutation (0 = even, 1 = odd)
+    pub fn edge_parity(&self) -> u8 {
+        permutation_parity(&self.ep)
+    }
+}
+
+// Corner orientations 3..5 only occur in the mirrored symmetry cubes.
+// A mirror reverses the direction of the twist of whatever it is combined with.
+fn mirrored_orientation(ori_a: u8, ori_b: u8) -> u8 {
+    if ori_a < 3 {
+        // b is mirrored, so is the result
+        3 + (ori_a + ori_b) % 3
+    } else if ori_b < 3 {
+        // a is mirrored, so is the result
+        3 + (ori_a + 3 - ori_b) % 3
+    } else {
+        // Two mirrors cancel out
+        (ori_a + 3 - ori_b) % 3
+    }
+}
+
+// Counts inversions; each inversion is one transposition
+fn permutation_parity(perm: &[u8]) -> u8 {
+    let mut parity = 0;
+    for i in 0..perm.len() {
+        for j in i + 1..perm.len() {
+            if perm[j] < perm[i] {
+                parity ^= 1;
+            }
+        }
+    }
+    parity
 }
 
 impl CubieCube {
@@ -338,14 +448,10 @@ impl CubieCube {
     pub fn get_slice_perm(&self) -> usize {
         let mut idx = 0;
         let mut val = [0; 4];
-        let mut k = 0;
 
         // Extract the 4 slice edges (indices 8..11 in standard notation)
         // We assume slice edges are already IN the slice positions.
-        for i in 8..12 {
-            val[k] = self.ep[i];
-            k += 1;
-        }
+        val.copy_from_slice(&self.ep[8..12]);
 
         // Lehmer code for 4 items
         for i in 0..3 {
@@ -360,18 +466,31 @@ impl CubieCube {
         idx
     }
 
+    /// Full Edge Permutation Coordinate (0..479001599)
+    /// Lehmer code for all 12 edges.
+    pub fn get_edge_perm(&self) -> usize {
+        let mut idx = 0;
+
+        for i in 0..11 {
+            let mut count = 0;
+            for j in i + 1..12 {
+                if self.ep[j] < self.ep[i] {
+                    count += 1;
+                }
+            }
+            idx = (idx + count) * (11 - i);
+        }
+        idx
+    }
+
     /// U/D Edge Permutation Coordinate (0..40319)
     /// Tracks the order of the 8 U/D edges (UR, UF, UL, UB, DR, DF, DL, DB)
     pub fn get_ud_edges(&self) -> usize {
         let mut idx = 0;
         let mut val = [0; 8];
-        let mut k = 0;
 
         // Extract the first 8 edges
-        for i in 0..8 {
-            val[k] = self.ep[i];
-            k += 1;
-        }
+        val.copy_from_slice(&self.ep[0..8]);
 
         // Lehmer code for 8 items
         for i in 0..7 {
@@ -410,6 +529,22 @@ impl CubieCube {
         cc
     }
 
+    /// Reconstructs the full Edge Permutation from index (0..479001599)
+    pub fn set_edge_perm(mut idx: usize) -> Self {
+        let mut cc = CubieCube::SOLVED;
+        let mut available = vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11];
+
+        for i in 0..11 {
+            let fact = FACTORIALS_11[11 - i];
+            let selected_idx = idx / fact;
+            idx %= fact;
+
+            cc.ep[i] = available.remove(selected_idx);
+        }
+        cc.ep[11] = available[0];
+        cc
+    }
+
     /// Reconstructs U/D Edge Permutation from index (0..40319)
     /// Sets edges 0..7 (UR, UF, UL, UB, DR, DF, DL, DB)
     pub fn set_ud_edges(mut idx: usize) -> Self {
@@ -436,8 +571,7 @@ impl CubieCube {
         // 4 items means we use factorials 3!, 2!, 1!
         let facts = [6, 2, 1];
 
-        for i in 0..3 {
-            let fact = facts[i];
+        for (i, &fact) in facts.iter().enumerate() {
             let selected_idx = idx / fact;
             idx %= fact;
 
@@ -448,3 +582,27 @@ impl CubieCube {
         cc
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edge_perm_bijection() {
+        let mut cube = CubieCube::SOLVED;
+        cube.apply_sequence("R U F' L2 D B' R2").unwrap();
+
+        let idx = cube.get_edge_perm();
+        assert!(idx < 479001600);
+        assert_eq!(CubieCube::set_edge_perm(idx).ep, cube.ep);
+        assert_eq!(CubieCube::set_edge_perm(479001599).get_edge_perm(), 479001599);
+    }
+
+    #[test]
+    fn test_random_state_is_valid() {
+        for seed in 0..1000 {
+            let state = CubieCube::random_state(&mut crate::rng::rng_from_seed(seed));
+            assert_eq!(state.validate(), Ok(()), "invalid state from seed {}", seed);
+        }
+    }
+}
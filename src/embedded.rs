@@ -0,0 +1,43 @@
+use crate::pruning_table::{ ArchivedPruningTables, PruningTables };
+
+// include_bytes! only guarantees byte alignment, the archive needs more
+#[repr(C, align(16))]
+struct Aligned<T: ?Sized>(T);
+
+static EMBEDDED_TABLES: &Aligned<[u8]> = &Aligned(*include_bytes!(concat!(env!("OUT_DIR"), "/pruning_tables.rkyv")));
+
+impl PruningTables {
+    /// The tables generated by the build script and compiled into the binary.
+    /// Nothing is generated or read from disk, so this is free to call.
+    ///
+    /// Set `RUBIKS_CUBE_EMBEDDED_ENCODING=mod3` while building to embed 2-bit tables.
+    pub fn embedded() -> &'static ArchivedPruningTables {
+        // Safety: the bytes were serialized from `PruningTables` by this crate's build script
+        unsafe { rkyv::access_unchecked::<ArchivedPruningTables>(&EMBEDDED_TABLES.0) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cubie_cube::CubieCube;
+    use crate::pruning_table::{ test_tables, TableLookup };
+    use crate::solver::Solver;
+
+    #[test]
+    fn test_embedded_tables_match_generated() {
+        let embedded = PruningTables::embedded();
+        let owned = test_tables();
+
+        let mut rng = crate::rng::rng_from_seed(18);
+        for _ in 0..20 {
+            let cube = CubieCube::random_state(&mut rng);
+            let (twist, flip, slice) = (cube.get_twist() as usize, cube.get_flip() as usize, cube.get_slice_sorted() as usize);
+            assert_eq!(embedded.phase1_depth(twist, flip, slice).unwrap(), owned.phase1_depth(twist, flip, slice).unwrap());
+            assert_eq!(
+                Solver::new(embedded).solve(&cube).map(|s| s.moves).ok(),
+                Solver::new(owned).solve(&cube).map(|s| s.moves).ok()
+            );
+        }
+    }
+}
@@ -0,0 +1,412 @@
+use crate::cubie_cube::{ Corner, CubieCube, Edge };
+
+use std::fmt;
+use std::str::FromStr;
+
+/// The six sticker colours, named after the face whose centre carries them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Color {
+    U,
+    R,
+    F,
+    D,
+    L,
+    B,
+}
+
+impl Color {
+    pub const ALL: [Color; 6] = [Color::U, Color::R, Color::F, Color::D, Color::L, Color::B];
+
+    pub fn from_char(c: char) -> Option<Color> {
+        match c {
+            'U' => Some(Color::U),
+            'R' => Some(Color::R),
+            'F' => Some(Color::F),
+            'D' => Some(Color::D),
+            'L' => Some(Color::L),
+            'B' => Some(Color::B),
+            _ => None,
+        }
+    }
+
+    pub fn to_char(self) -> char {
+        match self {
+            Color::U => 'U',
+            Color::R => 'R',
+            Color::F => 'F',
+            Color::D => 'D',
+            Color::L => 'L',
+            Color::B => 'B',
+        }
+    }
+}
+
+/// Returns the Kociemba name of a facelet index (0 -> "U1", 53 -> "B9").
+pub fn facelet_name(index: usize) -> String {
+    format!("{}{}", Color::ALL[index / 9].to_char(), index % 9 + 1)
+}
+
+/// The 54 facelets of the cube.
+///
+/// Faces are stored in the order U, R, F, D, L, B with 9 facelets each,
+/// numbered row by row as seen when looking straight at the face:
+///
+/// ```text
+///              |************|
+///              |*U1**U2**U3*|
+///              |************|
+///              |*U4**U5**U6*|
+///              |************|
+///              |*U7**U8**U9*|
+///              |************|
+/// |************|************|************|************|
+/// |*L1**L2**L3*|*F1**F2**F3*|*R1**R2**R3*|*B1**B2**B3*|
+/// |************|************|************|************|
+/// |*L4**L5**L6*|*F4**F5**F6*|*R4**R5**R6*|*B4**B5**B6*|
+/// |************|************|************|************|
+/// |*L7**L8**L9*|*F7**F8**F9*|*R7**R8**R9*|*B7**B8**B9*|
+/// |************|************|************|************|
+///              |************|
+///              |*D1**D2**D3*|
+///              |************|
+///              |*D4**D5**D6*|
+///              |************|
+///              |*D7**D8**D9*|
+///              |************|
+/// ```
+///
+/// So U1 is facelet 0, R1 is facelet 9, F1 is 18, D1 is 27, L1 is 36 and B1 is 45.
+/// The U and D faces are viewed with F at the bottom and top respectively.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FaceletCube {
+    pub f: [Color; 54],
+}
+
+// Facelets of each corner position, starting with the U/D sticker and going clockwise.
+const CORNER_FACELET: [[usize; 3]; 8] = [
+    [8, 9, 20], // URF: U9, R1, F3
+    [6, 18, 38], // UFL: U7, F1, L3
+    [0, 36, 47], // ULB: U1, L1, B3
+    [2, 45, 11], // UBR: U3, B1, R3
+    [29, 26, 15], // DFR: D3, F9, R7
+    [27, 44, 24], // DLF: D1, L9, F7
+    [33, 53, 42], // DBL: D7, B9, L7
+    [35, 17, 51], // DRB: D9, R9, B7
+];
+
+// Facelets of each edge position, the reference (U/D or F/B) sticker first.
+const EDGE_FACELET: [[usize; 2]; 12] = [
+    [5, 10], // UR: U6, R2
+    [7, 19], // UF: U8, F2
+    [3, 37], // UL: U4, L2
+    [1, 46], // UB: U2, B2
+    [32, 16], // DR: D6, R8
+    [28, 25], // DF: D2, F8
+    [30, 43], // DL: D4, L8
+    [34, 52], // DB: D8, B8
+    [23, 12], // FR: F6, R4
+    [21, 41], // FL: F4, L6
+    [50, 39], // BL: B6, L4
+    [48, 14], // BR: B4, R6
+];
+
+// Colours of each corner piece, in the same clockwise order as CORNER_FACELET.
+const CORNER_COLOR: [[Color; 3]; 8] = [
+    [Color::U, Color::R, Color::F],
+    [Color::U, Color::F, Color::L],
+    [Color::U, Color::L, Color::B],
+    [Color::U, Color::B, Color::R],
+    [Color::D, Color::F, Color::R],
+    [Color::D, Color::L, Color::F],
+    [Color::D, Color::B, Color::L],
+    [Color::D, Color::R, Color::B],
+];
+
+// Colours of each edge piece, in the same order as EDGE_FACELET.
+const EDGE_COLOR: [[Color; 2]; 12] = [
+    [Color::U, Color::R],
+    [Color::U, Color::F],
+    [Color::U, Color::L],
+    [Color::U, Color::B],
+    [Color::D, Color::R],
+    [Color::D, Color::F],
+    [Color::D, Color::L],
+    [Color::D, Color::B],
+    [Color::F, Color::R],
+    [Color::F, Color::L],
+    [Color::B, Color::L],
+    [Color::B, Color::R],
+];
+
+/// Reasons a set of stickers cannot be turned into cubies.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FaceletError {
+    /// A facelet string must have exactly 54 characters.
+    InvalidLength(usize),
+    /// The character at this facelet index is not one of U, R, F, D, L, B.
+    InvalidColor {
+        position: usize,
+        found: char,
+    },
+    /// Every colour must appear on exactly 9 facelets.
+    WrongColorCount {
+        color: Color,
+        count: usize,
+    },
+    /// The centre of this face does not carry the face's own colour.
+    WrongCenter {
+        face: Color,
+        found: Color,
+    },
+    /// The stickers at this corner position do not match any corner piece.
+    InvalidCorner(Corner),
+    /// The stickers at this edge position do not match any edge piece.
+    InvalidEdge(Edge),
+}
+
+impl fmt::Display for FaceletError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FaceletError::InvalidLength(len) => {
+                write!(f, "facelet string has {} characters, expected 54", len)
+            }
+            FaceletError::InvalidColor { position, found } => {
+                write!(f, "invalid colour '{}' at facelet {} ({})", found, position, facelet_name(*position))
+            }
+            FaceletError::WrongColorCount { color, count } => {
+                write!(f, "colour {} appears {} times, expected 9", color.to_char(), count)
+            }
+            FaceletError::WrongCenter { face, found } => {
+                write!(f, "centre of face {} is {}, expected {}", face.to_char(), found.to_char(), face.to_char())
+            }
+            FaceletError::InvalidCorner(c) => {
+                write!(f, "corner at {:?} has a colour combination that does not exist", c)
+            }
+            FaceletError::InvalidEdge(e) => {
+                write!(f, "edge at {:?} has a colour combination that does not exist", e)
+            }
+        }
+    }
+}
+
+impl std::error::Error for FaceletError {}
+
+impl FaceletCube {
+    pub const SOLVED: FaceletCube = {
+        let mut f = [Color::U; 54];
+        let mut i = 0;
+        while i < 54 {
+            f[i] = Color::ALL[i / 9];
+            i += 1;
+        }
+        FaceletCube { f }
+    };
+
+    /// Paints the stickers of a cubie-level state.
+    pub fn from_cubie(cc: &CubieCube) -> Self {
+        let mut fc = FaceletCube::SOLVED;
+
+        for (i, fac) in CORNER_FACELET.iter().enumerate() {
+            let piece = cc.cp[i] as usize;
+            let ori = cc.co[i] as usize;
+            for k in 0..3 {
+                fc.f[fac[(k + ori) % 3]] = CORNER_COLOR[piece][k];
+            }
+        }
+
+        for (i, fac) in EDGE_FACELET.iter().enumerate() {
+            let piece = cc.ep[i] as usize;
+            let ori = cc.eo[i] as usize;
+            for k in 0..2 {
+                fc.f[fac[(k + ori) % 2]] = EDGE_COLOR[piece][k];
+            }
+        }
+
+        fc
+    }
+
+    /// Identifies every corner and edge from its colours.
+    /// The result is not checked for solvability, only that the centres are in place and each piece exists.
+    pub fn to_cubie(&self) -> Result<CubieCube, FaceletError> {
+        // Pieces are decoded against the fixed colour scheme, so the centres must match it
+        for face in Color::ALL {
+            let found = self.f[4 + 9 * (face as usize)];
+            if found != face {
+                return Err(FaceletError::WrongCenter { face, found });
+            }
+        }
+
+        let mut cc = CubieCube::SOLVED;
+
+        for (i, fac) in CORNER_FACELET.iter().enumerate() {
+            // The orientation is the position of the U/D sticker
+            let ori = (0..3)
+                .find(|&o| matches!(self.f[fac[o]], Color::U | Color::D))
+                .ok_or(FaceletError::InvalidCorner(Corner::ALL[i]))?;
+            let col1 = self.f[fac[(ori + 1) % 3]];
+            let col2 = self.f[fac[(ori + 2) % 3]];
+
+            let piece = CORNER_COLOR.iter()
+                .position(|c| c[0] == self.f[fac[ori]] && c[1] == col1 && c[2] == col2)
+                .ok_or(FaceletError::InvalidCorner(Corner::ALL[i]))?;
+
+            cc.cp[i] = piece as u8;
+            cc.co[i] = ori as u8;
+        }
+
+        for (i, fac) in EDGE_FACELET.iter().enumerate() {
+            let a = self.f[fac[0]];
+            let b = self.f[fac[1]];
+
+            let (piece, ori) = EDGE_COLOR.iter()
+                .enumerate()
+                .find_map(|(j, c)| {
+                    if c[0] == a && c[1] == b {
+                        Some((j, 0))
+                    } else if c[0] == b && c[1] == a {
+                        Some((j, 1))
+                    } else {
+                        None
+                    }
+                })
+                .ok_or(FaceletError::InvalidEdge(Edge::ALL[i]))?;
+
+            cc.ep[i] = piece as u8;
+            cc.eo[i] = ori;
+        }
+
+        Ok(cc)
+    }
+}
+
+impl FromStr for FaceletCube {
+    type Err = FaceletError;
+
+    /// Parses the 54 character "URFDLB" facelet string (U1..U9, R1..R9, F1..F9, D1..D9, L1..L9, B1..B9).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let len = s.chars().count();
+        if len != 54 {
+            return Err(FaceletError::InvalidLength(len));
+        }
+
+        let mut fc = FaceletCube::SOLVED;
+        let mut counts = [0; 6];
+
+        for (i, c) in s.chars().enumerate() {
+            let color = Color::from_char(c).ok_or(FaceletError::InvalidColor {
+                position: i,
+                found: c,
+            })?;
+            fc.f[i] = color;
+            counts[color as usize] += 1;
+        }
+
+        for color in Color::ALL {
+            let count = counts[color as usize];
+            if count != 9 {
+                return Err(FaceletError::WrongColorCount { color, count });
+            }
+        }
+
+        Ok(fc)
+    }
+}
+
+impl fmt::Display for FaceletCube {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s: String = self.f
+            .iter()
+            .map(|c| c.to_char())
+            .collect();
+        write!(f, "{}", s)
+    }
+}
+
+impl CubieCube {
+    /// Builds a cube from a Kociemba facelet string, e.g. "UUUUUUUUURRRRRRRRRFFFFFFFFF...".
+    pub fn from_facelet_str(s: &str) -> Result<CubieCube, FaceletError> {
+        s.parse::<FaceletCube>()?.to_cubie()
+    }
+
+    /// Writes the cube as a Kociemba facelet string.
+    pub fn to_facelet_string(&self) -> String {
+        FaceletCube::from_cubie(self).to_string()
+    }
+}
+
+impl Default for FaceletCube {
+    fn default() -> Self {
+        Self::SOLVED
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::turn::Turn;
+
+    #[test]
+    fn test_facelet_round_trip() {
+        assert_eq!(FaceletCube::from_cubie(&CubieCube::SOLVED), FaceletCube::SOLVED);
+        assert_eq!(FaceletCube::SOLVED.to_cubie(), Ok(CubieCube::SOLVED));
+
+        // Every single move, then a longer sequence touching all faces
+        for m in Turn::ALL {
+            let state = m.to_cubie();
+            let facelets = FaceletCube::from_cubie(&state);
+            assert_eq!(facelets.to_cubie(), Ok(state), "Round trip failed for move {:?}", m);
+        }
+
+        let mut state = CubieCube::SOLVED;
+        state.apply_sequence("R U F' D2 L B' U2 R' F D L2 B").unwrap();
+        assert_eq!(FaceletCube::from_cubie(&state).to_cubie(), Ok(state));
+    }
+
+    #[test]
+    fn test_facelet_detects_bad_pieces() {
+        // Swap two stickers of the URF corner so it has two U/D-less colours
+        let mut facelets = FaceletCube::SOLVED;
+        facelets.f[8] = Color::R;
+        assert_eq!(facelets.to_cubie(), Err(FaceletError::InvalidCorner(Corner::URF)));
+
+        // Paint the UF edge with two U stickers
+        let mut facelets = FaceletCube::SOLVED;
+        facelets.f[19] = Color::U;
+        assert_eq!(facelets.to_cubie(), Err(FaceletError::InvalidEdge(Edge::UF)));
+
+        // Swap the U and F centres
+        let mut facelets = FaceletCube::SOLVED;
+        facelets.f[4] = Color::F;
+        facelets.f[22] = Color::U;
+        assert_eq!(facelets.to_cubie(), Err(FaceletError::WrongCenter { face: Color::U, found: Color::F }));
+    }
+
+    #[test]
+    fn test_facelet_string() {
+        let solved = "UUUUUUUUURRRRRRRRRFFFFFFFFFDDDDDDDDDLLLLLLLLLBBBBBBBBB";
+        assert_eq!(CubieCube::from_facelet_str(solved), Ok(CubieCube::SOLVED));
+        assert_eq!(CubieCube::SOLVED.to_facelet_string(), solved);
+
+        // A single R turn as produced by other Kociemba-format tools
+        let r_turn = "UUFUUFUUFRRRRRRRRRFFDFFDFFDDDBDDBDDBLLLLLLLLLUBBUBBUBB";
+        assert_eq!(CubieCube::from_facelet_str(r_turn), Ok(CubieCube::R));
+        assert_eq!(CubieCube::R.to_facelet_string(), r_turn);
+    }
+
+    #[test]
+    fn test_facelet_string_errors() {
+        assert_eq!(CubieCube::from_facelet_str("UUU"), Err(FaceletError::InvalidLength(3)));
+
+        let bad_char = "UUUUUUUUURRRRXRRRRFFFFFFFFFDDDDDDDDDLLLLLLLLLBBBBBBBBB";
+        assert_eq!(
+            CubieCube::from_facelet_str(bad_char),
+            Err(FaceletError::InvalidColor { position: 13, found: 'X' })
+        );
+
+        let bad_count = "UUUUUUUUURRRRURRRRFFFFFFFFFDDDDDDDDDLLLLLLLLLBBBBBBBBB";
+        assert_eq!(
+            CubieCube::from_facelet_str(bad_count),
+            Err(FaceletError::WrongColorCount { color: Color::U, count: 10 })
+        );
+    }
+}
@@ -0,0 +1,294 @@
+use crate::pruning_table::{ NibbleArray, PruningEncoding };
+
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{ AtomicU8, AtomicUsize, Ordering };
+use std::thread;
+use std::time::{ Duration, Instant };
+
+/// A step of table generation, as reported to `GenerationConfig::on_progress`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GenerationProgress {
+    /// Name of the `PruningTables` field being built, e.g. "flipslice_twist_pruning".
+    pub table: &'static str,
+    /// BFS depth just completed, `None` for tables that are not built by a search.
+    pub depth: Option<u8>,
+    /// Entries filled so far.
+    pub filled: usize,
+    /// Entries in the whole table.
+    pub total: usize,
+    /// Time since generation of the table set started.
+    pub elapsed: Duration,
+}
+
+type ProgressObserver = Arc<dyn Fn(&GenerationProgress) + Send + Sync>;
+
+/// Options for building the tables.
+///
+/// Not `PartialEq`/`Eq`: `backward_threshold` is an `f64` and the progress observer is a closure.
+#[derive(Clone)]
+pub struct GenerationConfig {
+    pub threads: usize,
+    pub backward_threshold: f64,
+    pub encoding: PruningEncoding,
+    observer: Option<ProgressObserver>,
+}
+
+impl Default for GenerationConfig {
+    fn default() -> Self {
+        Self {
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            backward_threshold: 0.3,
+            encoding: PruningEncoding::default(),
+            observer: None,
+        }
+    }
+}
+
+impl fmt::Debug for GenerationConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("GenerationConfig")
+            .field("threads", &self.threads)
+            .field("backward_threshold", &self.backward_threshold)
+            .field("encoding", &self.encoding)
+            .field("observer", &self.observer.as_ref().map(|_| "Fn"))
+            .finish()
+    }
+}
+
+impl GenerationConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of worker threads (at least 1). Defaults to the available parallelism.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Fraction of the table that must be filled before the BFS switches from expanding
+    /// the frontier to scanning the unvisited entries. Anything above 1.0 never switches.
+    pub fn backward_threshold(mut self, fraction: f64) -> Self {
+        self.backward_threshold = fraction;
+        self
+    }
+
+    /// How the finished pruning tables store their distances. Defaults to `Nibble`.
+    pub fn encoding(mut self, encoding: PruningEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Calls `observer` after every finished table and every BFS layer.
+    /// Generation is silent without one.
+    pub fn on_progress(mut self, observer: impl Fn(&GenerationProgress) + Send + Sync + 'static) -> Self {
+        self.observer = Some(Arc::new(observer));
+        self
+    }
+
+    pub(crate) fn reporter(&self) -> ProgressReporter<'_> {
+        ProgressReporter {
+            observer: self.observer.as_deref(),
+            started: Instant::now(),
+        }
+    }
+}
+
+/// Forwards progress to the configured observer, timing from its creation.
+pub(crate) struct ProgressReporter<'a> {
+    observer: Option<&'a (dyn Fn(&GenerationProgress) + Send + Sync)>,
+    started: Instant,
+}
+
+impl ProgressReporter<'_> {
+    pub(crate) fn report(&self, table: &'static str, depth: Option<u8>, filled: usize, total: usize) {
+        if let Some(observer) = self.observer {
+            observer(
+                &(GenerationProgress {
+                    table,
+                    depth,
+                    filled,
+                    total,
+                    elapsed: self.started.elapsed(),
+                })
+            );
+        }
+    }
+}
+
+/// Entries handed to a worker at a time. Small enough to balance uneven layers.
+const CHUNK: usize = 1 << 16;
+
+/// Runs `work(start, end)` over `0..size` in chunks on `threads` threads and sums the results.
+pub(crate) fn parallel_sum(size: usize, threads: usize, work: impl Fn(usize, usize) -> usize + Sync) -> usize {
+    let next = AtomicUsize::new(0);
+
+    let worker = || {
+        let mut total = 0;
+        loop {
+            let start = next.fetch_add(CHUNK, Ordering::Relaxed);
+            if start >= size {
+                return total;
+            }
+            total += work(start, (start + CHUNK).min(size));
+        }
+    };
+
+    if threads <= 1 {
+        return worker();
+    }
+    thread::scope(|s| {
+        let handles: Vec<_> = (0..threads).map(|_| s.spawn(worker)).collect();
+        handles
+            .into_iter()
+            .map(|h| h.join().expect("table generation thread panicked"))
+            .sum()
+    })
+}
+
+/// A `NibbleArray` that several threads can fill at once.
+/// Entries only ever go from unvisited (0xF) to a distance, never back.
+pub(crate) struct AtomicNibbleArray {
+    data: Vec<AtomicU8>,
+    length: usize,
+}
+
+impl AtomicNibbleArray {
+    pub(crate) fn new(size: usize) -> Self {
+        Self {
+            data: (0..size.div_ceil(2)).map(|_| AtomicU8::new(0xff)).collect(),
+            length: size,
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn get(&self, index: usize) -> u8 {
+        let byte = self.data[index / 2].load(Ordering::Relaxed);
+        if index.is_multiple_of(2) { byte & 0x0f } else { (byte >> 4) & 0x0f }
+    }
+
+    /// Stores `value` if the entry is still unvisited. Returns whether this call filled it.
+    #[inline(always)]
+    pub(crate) fn try_set(&self, index: usize, value: u8) -> bool {
+        let byte = &self.data[index / 2];
+        let shift = (index % 2) * 4;
+        let mut current = byte.load(Ordering::Relaxed);
+        loop {
+            if (current >> shift) & 0x0f != 0x0f {
+                return false;
+            }
+            let new = (current & !(0x0f << shift)) | (value << shift);
+            match byte.compare_exchange_weak(current, new, Ordering::Relaxed, Ordering::Relaxed) {
+                Ok(_) => {
+                    return true;
+                }
+                Err(actual) => {
+                    current = actual;
+                }
+            }
+        }
+    }
+
+    pub(crate) fn into_nibble_array(self) -> NibbleArray {
+        NibbleArray {
+            data: self.data
+                .into_iter()
+                .map(AtomicU8::into_inner)
+                .collect(),
+            length: self.length,
+        }
+    }
+}
+
+/// The graph a pruning table is the distance map of.
+pub(crate) trait SearchSpace: Sync {
+    fn size(&self) -> usize;
+
+    /// Calls `pred` with the entries one move away from `index` until it returns true.
+    /// Returns whether it did.
+    fn any_neighbour(&self, index: usize, pred: impl FnMut(usize) -> bool) -> bool;
+
+    /// Calls `visit` with every entry one move away from `index`.
+    fn for_each_neighbour(&self, index: usize, mut visit: impl FnMut(usize)) {
+        self.any_neighbour(index, |n| {
+            visit(n);
+            false
+        });
+    }
+
+    /// Calls `visit` with every other entry that must get the same distance as `index`
+    /// (symmetry-reduced tables store some states more than once).
+    fn for_each_equivalent(&self, _index: usize, _visit: impl FnMut(usize)) {}
+}
+
+/// Breadth-first distances from `start`, one layer at a time.
+///
+/// Every layer scans the table in parallel. While the table is mostly empty the entries at
+/// the current depth are expanded forwards. Once `config.backward_threshold` of it is filled,
+/// the unvisited entries look for a neighbour at the current depth instead, which is far
+/// cheaper when the frontier is huge and few entries are left.
+/// The moves must be closed under inverses so that the two directions agree.
+///
+/// Since BFS distances are unique, the result does not depend on the thread count or on
+/// the switch point. Entries deeper than `max_depth` are left unvisited (0xF).
+pub(crate) fn layered_bfs(
+    space: &impl SearchSpace,
+    start: usize,
+    max_depth: u8,
+    config: &GenerationConfig,
+    progress: &ProgressReporter,
+    name: &'static str
+) -> NibbleArray {
+    let size = space.size();
+    let table = AtomicNibbleArray::new(size);
+
+    table.try_set(start, 0);
+    let mut total = 1;
+    space.for_each_equivalent(start, |e| {
+        if table.try_set(e, 0) {
+            total += 1;
+        }
+    });
+    progress.report(name, Some(0), total, size);
+
+    for depth in 0..max_depth {
+        if total == size {
+            break;
+        }
+        let backward = total as f64 >= config.backward_threshold * size as f64;
+
+        let filled = parallel_sum(size, config.threads, |begin, end| {
+            let mut filled = 0;
+            let mut fill = |n: usize| {
+                if table.try_set(n, depth + 1) {
+                    filled += 1;
+                    space.for_each_equivalent(n, |e| {
+                        if table.try_set(e, depth + 1) {
+                            filled += 1;
+                        }
+                    });
+                }
+            };
+
+            for index in begin..end {
+                if backward {
+                    if table.get(index) == 0xf && space.any_neighbour(index, |n| table.get(n) == depth) {
+                        fill(index);
+                    }
+                } else if table.get(index) == depth {
+                    space.for_each_neighbour(index, &mut fill);
+                }
+            }
+            filled
+        });
+
+        if filled == 0 {
+            break;
+        }
+        total += filled;
+        progress.report(name, Some(depth + 1), total, size);
+    }
+
+    table.into_nibble_array()
+}
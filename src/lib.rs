@@ -1,4 +1,16 @@
+pub mod coord_cube;
+pub mod coordinate;
 pub mod cubie_cube;
+#[cfg(feature = "embedded-tables")]
+mod embedded;
+pub mod facelet_cube;
+pub mod generation;
 pub mod pruning_table;
+pub mod rng;
+pub mod scramble;
 pub mod solver;
+pub mod symmetry;
+pub mod table_cache;
+pub mod table_stats;
 pub mod turn;
+pub mod validation;
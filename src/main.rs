@@ -2,6 +2,8 @@ use rubiks_cube::cubie_cube::CubieCube;
 use rubiks_cube::pruning_table::PruningTables;
 use rubiks_cube::solver::Solver;
 
+use std::ops::ControlFlow;
+
 fn main() {
     let tables = PruningTables::new();
     let mut solver = Solver::new(&tables);
@@ -13,7 +15,7 @@ fn main() {
 
     // cube.apply_sequence(scramble_str).expect("Invalid scramble sequence");
 
-    let scramble_moves = cube.scramble(30);
+    let scramble_moves = cube.scramble(30, &mut rand::rng());
 
     let scramble_str: String = scramble_moves
         .iter()
@@ -23,17 +25,40 @@ fn main() {
 
     println!("Scramble: {}", scramble_str);
 
-    if let Some(solution) = solver.solve(&cube) {
-        println!("Solve Order: {}", solution);
+    let result = solver.solve_with(&cube, |better| {
+        println!("  -> Found better solution! Length: {:02} | Moves: {}", better.htm(), better);
+        ControlFlow::Continue(())
+    });
+    let solution = match result {
+        Ok(solution) => solution,
+        Err(e) => {
+            println!("FAILED! {}", e);
+            return;
+        }
+    };
+
+    println!("Solve Order: {}", solution);
+    println!(
+        "Length: {} HTM / {} QTM (phase 1: {}, phase 2: {})",
+        solution.htm(),
+        solution.qtm(),
+        solution.phase1().len(),
+        solution.phase2().len()
+    );
+    println!(
+        "Searched {} phase 1 and {} phase 2 nodes in {:?}",
+        solution.stats.phase1_nodes,
+        solution.stats.phase2_nodes,
+        solution.stats.elapsed
+    );
 
-        let mut verify_cube = CubieCube::SOLVED;
-        verify_cube.apply_sequence(&scramble_str).unwrap();
-        verify_cube.apply_sequence(&solution).unwrap();
+    let mut verify_cube = CubieCube::SOLVED;
+    verify_cube.apply_moves(&scramble_moves);
+    verify_cube.apply_moves(&solution.moves);
 
-        if verify_cube == CubieCube::SOLVED {
-            println!("SUCCESS! Solution is valid.");
-        } else {
-            println!("FAILED! State machine mismatch.");
-        }
+    if verify_cube == CubieCube::SOLVED {
+        println!("SUCCESS! Solution is valid.");
+    } else {
+        println!("FAILED! State machine mismatch.");
     }
 }
@@ -1,301 +1,803 @@
+use crate::coordinate::{ Coordinate, CornerPerm, Flip, SlicePerm, SliceSorted, Twist, UdEdges };
 use crate::cubie_cube::CubieCube;
+use crate::generation::{ self, GenerationConfig, ProgressReporter, SearchSpace };
+use crate::symmetry::{ self, ArchivedFlipSliceClasses, FlipSliceClasses, N_FLIPSLICE_CLASS, N_SYM_D4H };
+use crate::table_cache;
+use crate::table_stats::VerifyError;
 use crate::turn::Turn;
 
-use std::{ collections::VecDeque, fs::File, io::Read };
-use std::io::Write;
+use std::fmt;
 
 use rkyv::{ Archive, Deserialize, Serialize };
-use rkyv::rancor::Error;
 
 #[derive(Archive, Serialize, Deserialize)]
 pub struct PruningTables {
     // Phase 1 Move Tables
-    pub twist_move: Vec<Vec<u16>>, // [2187][18]
-    pub flip_move: Vec<Vec<u16>>, // [2048][18]
-    pub slice_move: Vec<Vec<u16>>, // [495][18]
+    pub twist_move: MoveTable, // [2187 * 18]
+    pub flip_move: MoveTable, // [2048 * 18]
+    pub slice_move: MoveTable, // [495 * 18]
 
-    pub twist_slice_pruning: NibbleArray,
-    pub flip_slice_pruning: NibbleArray,
+    // Phase 1 Symmetry Reduction
+    pub flipslice_classes: FlipSliceClasses,
+    pub twist_conj: Vec<u16>, // [2187 * 16]
+
+    // Phase 1 Pruning Table (Distance)
+    // Flip-Slice Class (64430) * Twist (2187) = 140,908,410 entries (~70MB with NibbleArray, ~35MB with Mod3Array)
+    pub flipslice_twist_pruning: PruningArray,
 
     // Phase 2 Move Tables
     // We use u16 because 8! = 40320, which fits in u16.
-    pub cp_move: Vec<Vec<u16>>, // [40320][18] Corner Permutation Move Table
-    pub ud_edge_move: Vec<Vec<u16>>, // [40320][18] U/D Edge Permutation Move Table
-    pub ep_slice_move: Vec<Vec<u16>>, // [24][18]    Slice Permutation Move Table (Small: 24)
+    pub cp_move: MoveTable, // [40320 * 18] Corner Permutation Move Table
+    pub ud_edge_move: MoveTable, // [40320 * 18] U/D Edge Permutation Move Table
+    pub ep_slice_move: MoveTable, // [24 * 18]    Slice Permutation Move Table (Small: 24)
 
     // Phase 2 Pruning Tables (Distance)
     // CP (40320) * Slice (24) = 967,680 entries (~483KB with NibbleArray)
-    pub corner_slice_pruning: NibbleArray,
+    pub corner_slice_pruning: PruningArray,
     // UD (40320) * Slice (24) = 967,680 entries (~483KB with NibbleArray)
-    pub ud_edge_slice_pruning: NibbleArray,
+    pub ud_edge_slice_pruning: PruningArray,
 }
 
 impl PruningTables {
+    /// Loads the tables from the default cache location, generating and caching them if needed.
+    /// See `table_cache::default_cache_path` for how the location is chosen.
+    // No `Default` impl: this can spend minutes generating tables and writes to disk
+    #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
-        let cache_path = "pruning_tables.rkyv";
-
-        if let Ok(mut file) = File::open(cache_path) {
-            // println!("Loading pruning tables from cache...");
-            let mut buffer = Vec::new();
-            if file.read_to_end(&mut buffer).is_ok() {
-                if let Ok(tables) = rkyv::from_bytes::<PruningTables, Error>(&buffer) {
-                    // println!("Successfully loaded tables.");
-                    return tables;
-                }
-            }
-            // println!("Cache corrupted or outdated. Regenerating...");
-        }
-
-        // println!("Generating pruning tables from scratch...");
-        let tables = Self::generate();
-
-        // println!("Saving pruning tables to disk...");
-        let bytes = rkyv::to_bytes::<Error>(&tables).expect("Failed to serialize tables");
-        if let Ok(mut file) = File::create(cache_path) {
-            let _ = file.write_all(&bytes);
-            // println!("Saved tables to {}.", cache_path);
-        }
+        Self::load_or_generate(table_cache::default_cache_path())
+    }
 
-        tables
+    /// Builds every table in memory without touching the disk, using all available cores.
+    pub fn generate() -> Self {
+        Self::generate_with(&GenerationConfig::default())
     }
 
-    fn generate() -> Self {
-        // Start by creating transistion tables for the pruning tables
-        let mut twist_move = vec![vec![0; 18]; 2187];
-        let mut flip_move = vec![vec![0; 18]; 2048];
-        let mut slice_move = vec![vec![0; 18]; 495];
+    /// Builds every table in memory with the given generation options.
+    pub fn generate_with(config: &GenerationConfig) -> Self {
+        let progress = config.reporter();
 
-        // Precompute the 18 Turn CubieCubes
-        // We map the Enum 0..17 to actual CubieCube structs to avoid re-generating them in loops
-        let moves: Vec<CubieCube> = Turn::ALL.iter()
-            .map(|m| m.to_cubie())
-            .collect();
+        // Start by creating transistion tables for the pruning tables
 
         // Generate Twist Turn Table (Size 2187 * 18)
         // The orientation is invarient so by the closure principle the last corner is entailed (3^7=2187).
-        for i in 0..2187 {
-            let state = CubieCube::set_twist(i as u16);
-            for (m_idx, m_cubie) in moves.iter().enumerate() {
-                let result = state.multiply(m_cubie);
-                twist_move[i][m_idx] = result.get_twist();
-            }
-        }
-        println!("Twist States: {:#?}", twist_move.len());
+        let twist_move = MoveTable::for_coordinate::<Twist>(config);
+        progress.report("twist_move", None, twist_move.data.len(), twist_move.data.len());
 
         // Generate Flip Turn Table (Size 2048 * 18)
         // The orientation is invarient so by the closure principle the last corner is entailed (2^11=2048).
-        for i in 0..2048 {
-            let state = CubieCube::set_flip(i as u16);
-            for (m_idx, m_cubie) in moves.iter().enumerate() {
-                let result = state.multiply(m_cubie);
-                flip_move[i][m_idx] = result.get_flip();
-            }
-        }
-        println!("Flip States: {:#?}", flip_move.len());
+        let flip_move = MoveTable::for_coordinate::<Flip>(config);
+        progress.report("flip_move", None, flip_move.data.len(), flip_move.data.len());
 
         // Generate Slice Sorted Turn Table (Size 495 * 18)
         // FR, FL, BL, BR are the 4 middle-layer slices we get the combination of 4 spots out of 12.
-        for i in 0..495 {
-            let state = CubieCube::set_slice_sorted(i as u16);
-            for (m_idx, m_cubie) in moves.iter().enumerate() {
-                let result = state.multiply(m_cubie);
-                slice_move[i][m_idx] = result.get_slice_sorted();
-            }
-        }
-        println!("Slice States: {:#?}", slice_move.len());
+        let slice_move = MoveTable::for_coordinate::<SliceSorted>(config);
+        progress.report("slice_move", None, slice_move.data.len(), slice_move.data.len());
 
-        println!("Generating Phase 1 Pruning...");
+        let flipslice_classes = FlipSliceClasses::generate();
+        let twist_conj = symmetry::twist_conjugation_table();
+        progress.report("flipslice_classes", None, flipslice_classes.rep.len(), N_FLIPSLICE_CLASS);
 
-        let twist_slice_pruning = Self::generate_pruning_table(
+        let flipslice_twist_pruning = Self::generate_flipslice_twist_pruning(
             &twist_move,
-            &slice_move,
-            2187,
-            495,
-            CubieCube::SOLVED.get_twist() as usize,
-            CubieCube::SOLVED.get_slice_sorted() as usize
-        );
-        println!("Twist-Slice States: {}", twist_slice_pruning.length);
-        println!("Twist-Slice Physical Bytes: {}", twist_slice_pruning.data.len());
-
-        let flip_slice_pruning = Self::generate_pruning_table(
             &flip_move,
             &slice_move,
-            2048,
-            495,
-            CubieCube::SOLVED.get_flip() as usize,
-            CubieCube::SOLVED.get_slice_sorted() as usize
+            &flipslice_classes,
+            &twist_conj,
+            config,
+            &progress
         );
-        println!("Flip-Slice States:  {}", flip_slice_pruning.length);
-        println!("Flip-Slice Physical Bytes:  {}", flip_slice_pruning.data.len());
 
         // Phase 2
         // For move tables, we calculate ALL 18 moves.
-        let mut cp_move = vec![vec![0; 18]; 40320];
-        let mut ud_edge_move = vec![vec![0; 18]; 40320];
-        let mut ep_slice_move = vec![vec![0; 18]; 24];
 
         // Generate Corner Permutation Move Table
         // Iterate through all 8! permutations
-        for i in 0..40320 {
-            let state = CubieCube::set_corner_perm(i);
-            for (m_idx, m_cubie) in moves.iter().enumerate() {
-                let result = state.multiply(m_cubie);
-                cp_move[i][m_idx] = result.get_corner_perm() as u16;
-            }
-        }
-        println!("Corner-Permutation States: {:#?}", cp_move.len());
+        let cp_move = MoveTable::for_coordinate::<CornerPerm>(config);
+        progress.report("cp_move", None, cp_move.data.len(), cp_move.data.len());
 
         // Generate U/D Edge Permutation Move Table
         // Iterate through all 8! permutations
-        for i in 0..40320 {
-            let state = CubieCube::set_ud_edges(i);
-            for (m_idx, m_cubie) in moves.iter().enumerate() {
-                let result = state.multiply(m_cubie);
-                ud_edge_move[i][m_idx] = result.get_ud_edges() as u16;
-            }
-        }
-        println!("U/D Edge Permutation States: {:#?}", ud_edge_move.len());
+        let ud_edge_move = MoveTable::for_coordinate::<UdEdges>(config);
+        progress.report("ud_edge_move", None, ud_edge_move.data.len(), ud_edge_move.data.len());
 
         // Generate Slice Permutation Move Table
         // Iterate through all 4! (24) permutations
-        for i in 0..24 {
-            let state = CubieCube::set_slice_perm(i);
-            for (m_idx, m_cubie) in moves.iter().enumerate() {
-                let result = state.multiply(m_cubie);
-                ep_slice_move[i][m_idx] = result.get_slice_perm() as u16;
-            }
-        }
-        println!("Slice Permutation States: {:#?}", ep_slice_move.len());
+        let ep_slice_move = MoveTable::for_coordinate::<SlicePerm>(config);
+        progress.report("ep_slice_move", None, ep_slice_move.data.len(), ep_slice_move.data.len());
 
-        // The Phase 2 Move Subset
-        // Indices corresponding to U, U2, U3, D, D2, D3, R2, L2, F2, B2
-        // U(0), U2(1), U3(2), D(9), D2(10), D3(11), R2(4), L2(13), F2(7), B2(16)
-        let phase2_moves = vec![0, 1, 2, 9, 10, 11, 4, 13, 7, 16];
-
-        println!("Generating Phase 2 Pruning...");
-
-        let corner_slice_pruning = Self::generate_phase2_pruning(
+        let corner_slice_pruning = generate_pair_pruning::<CornerPerm, SlicePerm>(
             &cp_move,
             &ep_slice_move,
-            40320,
-            24,
-            0,
-            0,
-            &phase2_moves
+            &Turn::PHASE2_MOVES,
+            config,
+            &progress,
+            "corner_slice_pruning"
         );
-        println!("Corner-Slice States:  {}", corner_slice_pruning.length);
-        println!("Corner-Slice Physical Bytes:  {}", corner_slice_pruning.data.len());
 
-        let ud_edge_slice_pruning = Self::generate_phase2_pruning(
+        let ud_edge_slice_pruning = generate_pair_pruning::<UdEdges, SlicePerm>(
             &ud_edge_move,
             &ep_slice_move,
-            40320,
-            24,
-            0,
-            0,
-            &phase2_moves
+            &Turn::PHASE2_MOVES,
+            config,
+            &progress,
+            "ud_edge_slice_pruning"
         );
-        println!("U/D Edge-Slice States:  {}", ud_edge_slice_pruning.length);
-        println!("U/D Edge-Slice Physical Bytes:  {}", ud_edge_slice_pruning.data.len());
 
+        // No state is more than 12 (Phase 1) or 14 (Phase 2 pairs) moves out, so every entry has a distance
+        let encode = |table| PruningArray::encode(table, config.encoding).expect("built-in pruning tables reach every entry");
         Self {
             twist_move,
             flip_move,
             slice_move,
-            twist_slice_pruning,
-            flip_slice_pruning,
+            flipslice_classes,
+            twist_conj,
+            flipslice_twist_pruning: encode(flipslice_twist_pruning),
             cp_move,
             ud_edge_move,
             ep_slice_move,
-            corner_slice_pruning,
-            ud_edge_slice_pruning,
+            corner_slice_pruning: encode(corner_slice_pruning),
+            ud_edge_slice_pruning: encode(ud_edge_slice_pruning),
         }
     }
 
-    fn generate_pruning_table(
-        move_table_1: &[Vec<u16>],
-        move_table_2: &[Vec<u16>],
-        num_states_1: usize,
-        num_states_2: usize,
-        start_idx_1: usize,
-        start_idx_2: usize
+    /// How the pruning tables store their distances.
+    pub fn encoding(&self) -> PruningEncoding {
+        self.flipslice_twist_pruning.encoding()
+    }
+
+    /// BFS over (flip-slice class, twist) pairs.
+    /// Each entry stands for every state whose flip-slice reduces to the class representative
+    /// with the given (conjugated) twist, so only the representatives are ever expanded.
+    fn generate_flipslice_twist_pruning(
+        twist_move: &MoveTable,
+        flip_move: &MoveTable,
+        slice_move: &MoveTable,
+        classes: &FlipSliceClasses,
+        twist_conj: &[u16],
+        config: &GenerationConfig,
+        progress: &ProgressReporter
     ) -> NibbleArray {
-        let size = num_states_1 * num_states_2;
-        // Initialize with 0xF (15), which represents "unvisited"
-        let mut table = NibbleArray::new(size, 0xf);
-        let mut queue = VecDeque::new();
-
-        // Initialize solved state (distance 0)
-        let start_combined = start_idx_1 * num_states_2 + start_idx_2;
-        table.set(start_combined, 0);
-        queue.push_back(start_combined);
-
-        // BFS
-        while let Some(current_combined) = queue.pop_front() {
-            // We get the distance of the current node
-            let dist = table.get(current_combined);
-
-            // Phase 1 max depth is ~12, so we won't overflow 15.
-            if dist >= 14 {
-                continue;
+        let space = FlipSliceTwistSpace::new(twist_move, flip_move, slice_move, classes, twist_conj);
+        generation::layered_bfs(&space, space.solved_index(), MAX_DEPTH, config, progress, "flipslice_twist_pruning")
+    }
+}
+
+/// Builds the joint distance table of two coordinates under a subset of the moves.
+///
+/// The entry of a cube is at `A::get(cube) * B::COUNT + B::get(cube)` and holds the number of
+/// `moves` needed to bring both coordinates to their values on the solved cube.
+/// Distances beyond 14 are left at 0xF, and such a table cannot be re-encoded with
+/// `PruningEncoding::Mod3` (`PruningArray::encode` returns an error).
+///
+/// `moves` need not be closed under inverses, but subsets that are not (e.g. `[U, R]`)
+/// are generated forwards only, ignoring `GenerationConfig::backward_threshold`.
+pub fn pair_pruning_table<A: Coordinate, B: Coordinate>(
+    move_a: &MoveTable,
+    move_b: &MoveTable,
+    moves: &[Turn],
+    config: &GenerationConfig
+) -> NibbleArray {
+    generate_pair_pruning::<A, B>(move_a, move_b, moves, config, &config.reporter(), "pair_pruning")
+}
+
+fn generate_pair_pruning<A: Coordinate, B: Coordinate>(
+    move_a: &MoveTable,
+    move_b: &MoveTable,
+    moves: &[Turn],
+    config: &GenerationConfig,
+    progress: &ProgressReporter,
+    name: &'static str
+) -> NibbleArray {
+    // The BFS grows outwards from solved, so it has to follow the inverse moves to find
+    // the distance back to solved. The backward scan looks at the neighbours in the other
+    // direction, which only agree with the BFS when the moves are closed under inverses.
+    let inverses: Vec<Turn> = moves
+        .iter()
+        .map(|m| m.inverse())
+        .collect();
+    let closed = inverses.iter().all(|m| moves.contains(m));
+    let forward_only;
+    let config = if closed {
+        config
+    } else {
+        forward_only = config.clone().backward_threshold(f64::INFINITY);
+        &forward_only
+    };
+
+    let space = PairSpace::new(move_a, move_b, &inverses);
+    let start = A::get(&CubieCube::SOLVED) * B::COUNT + B::get(&CubieCube::SOLVED);
+    // Max Phase 2 depth is usually < 18, entries further than 14 stay unvisited
+    generation::layered_bfs(&space, start, MAX_DEPTH, config, progress, name)
+}
+
+/// Move table reads shared by owned and archived tables, so the search spaces
+/// (and `verify`) work on either.
+pub(crate) trait MoveLookup: Sync {
+    fn states(&self) -> usize;
+    fn next(&self, state: usize, m: usize) -> u16;
+}
+
+impl MoveLookup for MoveTable {
+    fn states(&self) -> usize {
+        self.states
+    }
+
+    #[inline(always)]
+    fn next(&self, state: usize, m: usize) -> u16 {
+        self.get(state, m)
+    }
+}
+
+impl MoveLookup for ArchivedMoveTable {
+    fn states(&self) -> usize {
+        self.states.to_native() as usize
+    }
+
+    #[inline(always)]
+    fn next(&self, state: usize, m: usize) -> u16 {
+        self.get(state, m)
+    }
+}
+
+/// Flip-slice class reads shared by owned and archived classes.
+pub(crate) trait ClassLookup: Sync {
+    /// Class of a flip-slice index and the symmetry that maps it to the representative.
+    fn class_of(&self, flipslice: usize) -> (usize, usize);
+    /// Flip-slice index of the representative of a class.
+    fn rep(&self, class: usize) -> usize;
+    /// Bitmask of the symmetries that leave the representative unchanged.
+    fn rep_sym(&self, class: usize) -> u16;
+}
+
+// Both implementations read the same fields, only the stored integer types differ
+macro_rules! impl_class_lookup {
+    ($classes:ty) => {
+        impl ClassLookup for $classes {
+            #[inline(always)]
+            fn class_of(&self, flipslice: usize) -> (usize, usize) {
+                (u16::from(self.classidx[flipslice]) as usize, self.sym[flipslice] as usize)
+            }
+
+            #[inline(always)]
+            fn rep(&self, class: usize) -> usize {
+                u32::from(self.rep[class]) as usize
+            }
+
+            #[inline(always)]
+            fn rep_sym(&self, class: usize) -> u16 {
+                u16::from(self.rep_sym[class])
+            }
+        }
+    };
+}
+
+impl_class_lookup!(FlipSliceClasses);
+impl_class_lookup!(ArchivedFlipSliceClasses);
+
+/// The Phase 1 search space, (flip-slice class, twist) pairs.
+/// `W` is the stored twist conjugation entry, `u16` or its archived form.
+pub(crate) struct FlipSliceTwistSpace<'a, M: ?Sized, C: ?Sized, W> {
+    twist_move: &'a M,
+    flip_move: &'a M,
+    slice_move: &'a M,
+    classes: &'a C,
+    twist_conj: &'a [W],
+}
+
+impl<'a, M, C, W> FlipSliceTwistSpace<'a, M, C, W>
+    where M: MoveLookup + ?Sized, C: ClassLookup + ?Sized, W: Copy + Sync, u16: From<W>
+{
+    pub(crate) fn new(
+        twist_move: &'a M,
+        flip_move: &'a M,
+        slice_move: &'a M,
+        classes: &'a C,
+        twist_conj: &'a [W]
+    ) -> Self {
+        Self { twist_move, flip_move, slice_move, classes, twist_conj }
+    }
+
+    /// Table index of the solved state.
+    pub(crate) fn solved_index(&self) -> usize {
+        let solved = CubieCube::SOLVED;
+        self.index_of(solved.get_twist() as usize, solved.get_flip() as usize, solved.get_slice_sorted() as usize)
+    }
+
+    #[inline(always)]
+    fn conj(&self, twist: usize, sym: usize) -> usize {
+        u16::from(self.twist_conj[twist * N_SYM_D4H + sym]) as usize
+    }
+
+    // Maps a raw (twist, flip, slice) state to its table index
+    fn index_of(&self, twist: usize, flip: usize, slice: usize) -> usize {
+        let (class, sym) = self.classes.class_of(slice * 2048 + flip);
+        class * 2187 + self.conj(twist, sym)
+    }
+}
+
+impl<M, C, W> SearchSpace for FlipSliceTwistSpace<'_, M, C, W>
+    where M: MoveLookup + ?Sized, C: ClassLookup + ?Sized, W: Copy + Sync, u16: From<W>
+{
+    fn size(&self) -> usize {
+        N_FLIPSLICE_CLASS * 2187
+    }
+
+    fn any_neighbour(&self, index: usize, mut pred: impl FnMut(usize) -> bool) -> bool {
+        let class = index / 2187;
+        let twist = index % 2187;
+        let rep = self.classes.rep(class);
+        let flip = rep % 2048;
+        let slice = rep / 2048;
+
+        (0..18).any(|m_idx| {
+            pred(
+                self.index_of(
+                    self.twist_move.next(twist, m_idx) as usize,
+                    self.flip_move.next(flip, m_idx) as usize,
+                    self.slice_move.next(slice, m_idx) as usize
+                )
+            )
+        })
+    }
+
+    // A representative fixed by some symmetries appears with several equivalent twists.
+    // All of them must receive the same distance.
+    fn for_each_equivalent(&self, index: usize, mut visit: impl FnMut(usize)) {
+        let class = index / 2187;
+        let twist = index % 2187;
+        let mut fixed = self.classes.rep_sym(class) >> 1;
+        let mut s = 1;
+        while fixed != 0 {
+            if fixed & 1 == 1 {
+                visit(class * 2187 + self.conj(twist, s));
+            }
+            fixed >>= 1;
+            s += 1;
+        }
+    }
+}
+
+/// The product of two move tables under a subset of the moves, as used by Phase 2.
+pub(crate) struct PairSpace<'a, M: ?Sized> {
+    table1: &'a M,
+    table2: &'a M,
+    allowed_moves: &'a [Turn],
+}
+
+impl<'a, M: MoveLookup + ?Sized> PairSpace<'a, M> {
+    pub(crate) fn new(table1: &'a M, table2: &'a M, allowed_moves: &'a [Turn]) -> Self {
+        Self { table1, table2, allowed_moves }
+    }
+}
+
+impl<M: MoveLookup + ?Sized> SearchSpace for PairSpace<'_, M> {
+    fn size(&self) -> usize {
+        self.table1.states() * self.table2.states()
+    }
+
+    fn any_neighbour(&self, index: usize, mut pred: impl FnMut(usize) -> bool) -> bool {
+        let size2 = self.table2.states();
+        let idx1 = index / size2;
+        let idx2 = index % size2;
+
+        // Only iterate allowed G1 moves
+        self.allowed_moves.iter().any(|&m| {
+            let next1 = self.table1.next(idx1, m as usize) as usize;
+            let next2 = self.table2.next(idx2, m as usize) as usize;
+            pred(next1 * size2 + next2)
+        })
+    }
+}
+
+/// Read access to everything the solver looks up during search.
+///
+/// Implemented by the owned `PruningTables` and by `ArchivedPruningTables`, so the
+/// solver can run directly on a memory-mapped cache without deserializing it.
+pub trait TableLookup {
+    fn twist_move(&self, twist: usize, m: usize) -> u16;
+    fn flip_move(&self, flip: usize, m: usize) -> u16;
+    fn slice_move(&self, slice: usize, m: usize) -> u16;
+    fn corner_move(&self, corner: usize, m: usize) -> u16;
+    fn ud_edge_move(&self, ud_edges: usize, m: usize) -> u16;
+    fn slice_perm_move(&self, slice_perm: usize, m: usize) -> u16;
+
+    /// Exact Phase 1 distance of a state from its twist, flip and slice coordinates.
+    /// Fails if a `Mod3` table cannot be followed down to solved, which means it is corrupt.
+    fn phase1_depth(&self, twist: usize, flip: usize, slice: usize) -> Result<u8, VerifyError>;
+
+    /// Exact Phase 1 distance of a state one move away from a state at distance `parent`.
+    /// Much cheaper than `phase1_depth` when the table only stores distances mod 3.
+    /// Fails if the stored value cannot be one move from `parent`, which means the table is corrupt.
+    fn phase1_depth_from(&self, parent: u8, twist: usize, flip: usize, slice: usize) -> Result<u8, VerifyError>;
+
+    /// Distances of a G1 state in the corner-slice and edge-slice Phase 2 tables.
+    /// Fails like `phase1_depth` on a corrupt `Mod3` table.
+    fn phase2_depths(&self, corner: usize, ud_edges: usize, slice_perm: usize) -> Result<[u8; 2], VerifyError>;
+
+    /// Like `phase2_depths`, for a state one G1 move away from a state at distances `parent`.
+    fn phase2_depths_from(&self, parent: [u8; 2], corner: usize, ud_edges: usize, slice_perm: usize) -> Result<[u8; 2], VerifyError>;
+
+    /// Lower bound on the Phase 2 distance of a G1 state.
+    fn phase2_depth(&self, corner: usize, ud_edges: usize, slice_perm: usize) -> Result<u8, VerifyError> {
+        let [corner_depth, edge_depth] = self.phase2_depths(corner, ud_edges, slice_perm)?;
+        Ok(std::cmp::max(corner_depth, edge_depth))
+    }
+}
+
+// The slice coordinate with the four slice edges in the slice
+const SOLVED_SLICE: usize = 494;
+
+// Generation stops here, so no table stores a larger distance
+const MAX_DEPTH: u8 = 0xe;
+
+/// Exact distance of `start` from the goal.
+/// Nibble tables store it directly. Mod 3 tables are walked down to the goal,
+/// always moving to a neighbour one closer, and the steps are counted.
+/// A walk that gets stuck or runs longer than any stored distance means the table is corrupt.
+#[allow(clippy::too_many_arguments)]
+fn exact_depth<S: Copy>(
+    table: &'static str,
+    encoding: PruningEncoding,
+    index: impl Fn(S) -> usize,
+    value: impl Fn(usize) -> u8,
+    start: S,
+    is_goal: impl Fn(S) -> bool,
+    moves: &[Turn],
+    apply: impl Fn(S, usize) -> S
+) -> Result<u8, VerifyError> {
+    if encoding == PruningEncoding::Nibble {
+        return Ok(value(index(start)));
+    }
+
+    let mut state = start;
+    let mut depth = 0;
+    while !is_goal(state) {
+        let residue = value(index(state));
+        let closer = (residue + 2) % 3;
+        let next = moves
+            .iter()
+            .map(|&m| apply(state, m as usize))
+            .find(|&next| value(index(next)) == closer);
+        match next {
+            Some(next) if depth < MAX_DEPTH => {
+                state = next;
+                depth += 1;
+            }
+            _ => {
+                return Err(VerifyError::NoPredecessor { table, index: index(state), depth: residue });
+            }
+        }
+    }
+    Ok(depth)
+}
+
+// Both implementations read the same fields, only the stored integer types differ
+macro_rules! impl_table_lookup {
+    ($tables:ty) => {
+        impl $tables {
+            #[inline(always)]
+            fn phase1_index(&self, twist: usize, flip: usize, slice: usize) -> usize {
+                let flipslice = slice * 2048 + flip;
+                let class = u16::from(self.flipslice_classes.classidx[flipslice]) as usize;
+                let sym = self.flipslice_classes.sym[flipslice] as usize;
+                let twist_rep = u16::from(self.twist_conj[twist * N_SYM_D4H + sym]) as usize;
+                class * 2187 + twist_rep
+            }
+        }
+
+        impl TableLookup for $tables {
+            #[inline(always)]
+            fn twist_move(&self, twist: usize, m: usize) -> u16 {
+                self.twist_move.get(twist, m)
+            }
+
+            #[inline(always)]
+            fn flip_move(&self, flip: usize, m: usize) -> u16 {
+                self.flip_move.get(flip, m)
+            }
+
+            #[inline(always)]
+            fn slice_move(&self, slice: usize, m: usize) -> u16 {
+                self.slice_move.get(slice, m)
+            }
+
+            #[inline(always)]
+            fn corner_move(&self, corner: usize, m: usize) -> u16 {
+                self.cp_move.get(corner, m)
+            }
+
+            #[inline(always)]
+            fn ud_edge_move(&self, ud_edges: usize, m: usize) -> u16 {
+                self.ud_edge_move.get(ud_edges, m)
+            }
+
+            #[inline(always)]
+            fn slice_perm_move(&self, slice_perm: usize, m: usize) -> u16 {
+                self.ep_slice_move.get(slice_perm, m)
+            }
+
+            fn phase1_depth(&self, twist: usize, flip: usize, slice: usize) -> Result<u8, VerifyError> {
+                let table = &self.flipslice_twist_pruning;
+                exact_depth(
+                    "flipslice_twist_pruning",
+                    table.encoding(),
+                    |(t, f, s)| self.phase1_index(t, f, s),
+                    |i| table.get(i),
+                    (twist, flip, slice),
+                    |(t, f, s)| t == 0 && f == 0 && s == SOLVED_SLICE,
+                    &Turn::ALL,
+                    |(t, f, s), m| {
+                        (
+                            self.twist_move(t, m) as usize,
+                            self.flip_move(f, m) as usize,
+                            self.slice_move(s, m) as usize,
+                        )
+                    }
+                )
+            }
+
+            #[inline(always)]
+            fn phase1_depth_from(&self, parent: u8, twist: usize, flip: usize, slice: usize) -> Result<u8, VerifyError> {
+                let index = self.phase1_index(twist, flip, slice);
+                self.flipslice_twist_pruning.step("flipslice_twist_pruning", parent, index)
+            }
+
+            fn phase2_depths(&self, corner: usize, ud_edges: usize, slice_perm: usize) -> Result<[u8; 2], VerifyError> {
+                let corners = &self.corner_slice_pruning;
+                let edges = &self.ud_edge_slice_pruning;
+                Ok([
+                    exact_depth(
+                        "corner_slice_pruning",
+                        corners.encoding(),
+                        |(c, s)| c * 24 + s,
+                        |i| corners.get(i),
+                        (corner, slice_perm),
+                        |(c, s)| c == 0 && s == 0,
+                        &Turn::PHASE2_MOVES,
+                        |(c, s), m| (self.corner_move(c, m) as usize, self.slice_perm_move(s, m) as usize)
+                    )?,
+                    exact_depth(
+                        "ud_edge_slice_pruning",
+                        edges.encoding(),
+                        |(e, s)| e * 24 + s,
+                        |i| edges.get(i),
+                        (ud_edges, slice_perm),
+                        |(e, s)| e == 0 && s == 0,
+                        &Turn::PHASE2_MOVES,
+                        |(e, s), m| (self.ud_edge_move(e, m) as usize, self.slice_perm_move(s, m) as usize)
+                    )?,
+                ])
+            }
+
+            #[inline(always)]
+            fn phase2_depths_from(
+                &self,
+                parent: [u8; 2],
+                corner: usize,
+                ud_edges: usize,
+                slice_perm: usize
+            ) -> Result<[u8; 2], VerifyError> {
+                Ok([
+                    self.corner_slice_pruning.step("corner_slice_pruning", parent[0], corner * 24 + slice_perm)?,
+                    self.ud_edge_slice_pruning.step("ud_edge_slice_pruning", parent[1], ud_edges * 24 + slice_perm)?,
+                ])
             }
+        }
+    };
+}
+
+impl_table_lookup!(PruningTables);
+impl_table_lookup!(ArchivedPruningTables);
+
+/// A move table stored as one contiguous array, indexed `state * 18 + move`.
+#[derive(Clone, Archive, Serialize, Deserialize)]
+pub struct MoveTable {
+    pub data: Vec<u16>,
+    pub states: usize,
+}
 
-            let idx_1 = current_combined / num_states_2;
-            let idx_2 = current_combined % num_states_2;
+impl MoveTable {
+    /// Builds the table of a coordinate under all 18 moves, by applying every move to a
+    /// representative cube of each value. The values are split evenly between the configured threads.
+    pub fn for_coordinate<C: Coordinate>(config: &GenerationConfig) -> Self {
+        assert!(C::COUNT <= 1 << 16, "move tables store coordinates as u16");
 
-            for move_idx in 0..18 {
-                let next_1 = move_table_1[idx_1][move_idx] as usize;
-                let next_2 = move_table_2[idx_2][move_idx] as usize;
-                let next_combined = next_1 * num_states_2 + next_2;
+        let states = C::COUNT;
+        let moves = Turn::ALL.map(|m| m.to_cubie());
+        let mut data = vec![0; states * 18];
+        let rows_per_thread = states.div_ceil(config.threads).max(1);
 
-                // Check if unvisited (0xF)
-                if table.get(next_combined) == 0xf {
-                    table.set(next_combined, dist + 1);
-                    queue.push_back(next_combined);
+        let fill = |first: usize, block: &mut [u16]| {
+            for (i, row) in block.chunks_mut(18).enumerate() {
+                let state = C::set(first + i);
+                for (m_idx, m_cubie) in moves.iter().enumerate() {
+                    row[m_idx] = C::get(&state.multiply(m_cubie)) as u16;
                 }
             }
+        };
+
+        std::thread::scope(|s| {
+            for (t, block) in data.chunks_mut(rows_per_thread * 18).enumerate() {
+                let fill = &fill;
+                s.spawn(move || fill(t * rows_per_thread, block));
+            }
+        });
+        Self { data, states }
+    }
+
+    /// Get the state reached by applying move `m` (index into `Turn::ALL`) to `state`
+    #[inline(always)]
+    pub fn get(&self, state: usize, m: usize) -> u16 {
+        self.data[state * 18 + m]
+    }
+}
+
+impl ArchivedMoveTable {
+    #[inline(always)]
+    pub fn get(&self, state: usize, m: usize) -> u16 {
+        self.data[state * 18 + m].to_native()
+    }
+}
+
+/// How pruning table entries are stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum PruningEncoding {
+    /// The exact distance in 4 bits per entry.
+    #[default]
+    Nibble,
+    /// The distance mod 3 in 2 bits per entry. Exact distances are recovered from a
+    /// neighbour's distance, which the solver always knows.
+    Mod3,
+}
+
+impl PruningEncoding {
+    /// Bits used to store one entry.
+    pub const fn bits(self) -> u32 {
+        match self {
+            PruningEncoding::Nibble => 4,
+            PruningEncoding::Mod3 => 2,
         }
-        table
     }
+}
 
-    fn generate_phase2_pruning(
-        table1: &[Vec<u16>],
-        table2: &[Vec<u16>],
-        size1: usize,
-        size2: usize,
-        start1: usize,
-        start2: usize,
-        allowed_moves: &[usize]
-    ) -> NibbleArray {
-        let mut pruning = NibbleArray::new(size1 * size2, 0xf);
-        let mut queue = std::collections::VecDeque::new();
-
-        let start_node = start1 * size2 + start2;
-        pruning.set(start_node, 0);
-        queue.push_back(start_node);
-
-        while let Some(curr) = queue.pop_front() {
-            let dist = pruning.get(curr);
-            if dist >= 14 {
-                continue;
-            } // Max Phase 2 depth is usually < 18
-
-            let idx1 = curr / size2;
-            let idx2 = curr % size2;
-
-            // Only iterate allowed G1 moves
-            for &m_idx in allowed_moves {
-                let next1 = table1[idx1][m_idx] as usize;
-                let next2 = table2[idx2][m_idx] as usize;
-                let next_node = next1 * size2 + next2;
-
-                if pruning.get(next_node) == 0xf {
-                    pruning.set(next_node, dist + 1);
-                    queue.push_back(next_node);
+/// A pruning table in either encoding.
+#[derive(Clone, Archive, Serialize, Deserialize)]
+pub enum PruningArray {
+    Nibble(NibbleArray),
+    Mod3(Mod3Array),
+}
+
+impl PruningArray {
+    /// Stores the distances of a fully generated table in the given encoding.
+    /// Fails for `Mod3` if any entry was never reached (still 0xF).
+    pub fn encode(table: NibbleArray, encoding: PruningEncoding) -> Result<Self, UnreachedEntry> {
+        Ok(match encoding {
+            PruningEncoding::Nibble => PruningArray::Nibble(table),
+            PruningEncoding::Mod3 => PruningArray::Mod3(Mod3Array::from_distances(&table)?),
+        })
+    }
+
+    /// Number of entries.
+    pub fn length(&self) -> usize {
+        match self {
+            PruningArray::Nibble(t) => t.length,
+            PruningArray::Mod3(t) => t.length,
+        }
+    }
+}
+
+// The distance of an entry whose neighbour is at `parent`, from its own distance mod 3.
+// None if that is not a distance any table stores.
+#[inline(always)]
+fn mod3_step(parent: u8, residue: u8) -> Option<u8> {
+    if residue > 2 {
+        return None;
+    }
+    let depth = match (residue + 3 - parent % 3) % 3 {
+        0 => Some(parent),
+        1 => parent.checked_add(1),
+        _ => parent.checked_sub(1),
+    }?;
+    (depth <= MAX_DEPTH).then_some(depth)
+}
+
+macro_rules! impl_pruning_array {
+    ($array:ty) => {
+        impl $array {
+            pub fn encoding(&self) -> PruningEncoding {
+                match self {
+                    Self::Nibble(_) => PruningEncoding::Nibble,
+                    Self::Mod3(_) => PruningEncoding::Mod3,
                 }
             }
+
+            /// The stored value: the distance, or the distance mod 3 for `Mod3` tables.
+            #[inline(always)]
+            pub fn get(&self, index: usize) -> u8 {
+                match self {
+                    Self::Nibble(t) => t.get(index),
+                    Self::Mod3(t) => t.get(index),
+                }
+            }
+
+            /// Distance of an entry one move away from an entry at distance `parent`.
+            /// None if the stored value cannot be that, which means the table is corrupt.
+            #[inline(always)]
+            pub fn depth_from(&self, parent: u8, index: usize) -> Option<u8> {
+                match self {
+                    Self::Nibble(t) => Some(t.get(index)),
+                    Self::Mod3(t) => mod3_step(parent, t.get(index)),
+                }
+            }
+
+            // `depth_from` with the table name attached to the error
+            #[inline(always)]
+            fn step(&self, table: &'static str, parent: u8, index: usize) -> Result<u8, VerifyError> {
+                self.depth_from(parent, index)
+                    .ok_or_else(|| VerifyError::BadStep { table, index, parent, value: self.get(index) })
+            }
+        }
+    };
+}
+
+impl_pruning_array!(PruningArray);
+impl_pruning_array!(ArchivedPruningArray);
+
+impl ArchivedPruningArray {
+    /// Number of entries.
+    pub fn length(&self) -> usize {
+        match self {
+            ArchivedPruningArray::Nibble(t) => t.length.to_native() as usize,
+            ArchivedPruningArray::Mod3(t) => t.length.to_native() as usize,
         }
-        pruning
+    }
+}
+
+/// A distance table entry that was never reached, so it has no distance mod 3.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnreachedEntry {
+    pub index: usize,
+}
+
+impl fmt::Display for UnreachedEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "entry {} was never reached and has no distance mod 3", self.index)
+    }
+}
+
+impl std::error::Error for UnreachedEntry {}
+
+/// Distances mod 3, four entries per byte.
+#[derive(Clone, Archive, Serialize, Deserialize)]
+pub struct Mod3Array {
+    pub data: Vec<u8>,
+    pub length: usize,
+}
+
+impl Mod3Array {
+    /// Keeps the distances of `table` mod 3. Fails on the first entry that was never reached.
+    pub fn from_distances(table: &NibbleArray) -> Result<Self, UnreachedEntry> {
+        let mut data = vec![0; table.length.div_ceil(4)];
+        for i in 0..table.length {
+            let depth = table.get(i);
+            if depth == 0xf {
+                return Err(UnreachedEntry { index: i });
+            }
+            data[i / 4] |= (depth % 3) << ((i % 4) * 2);
+        }
+        Ok(Self { data, length: table.length })
+    }
+
+    #[inline(always)]
+    pub fn get(&self, index: usize) -> u8 {
+        (self.data[index / 4] >> ((index % 4) * 2)) & 0b11
+    }
+}
+
+impl ArchivedMod3Array {
+    #[inline(always)]
+    pub fn get(&self, index: usize) -> u8 {
+        (self.data[index / 4] >> ((index % 4) * 2)) & 0b11
     }
 }
 
@@ -308,7 +810,7 @@ pub struct NibbleArray {
 impl NibbleArray {
     pub fn new(size: usize, default: u8) -> Self {
         // We need ceil(size / 2) bytes
-        let num_bytes = (size + 1) / 2;
+        let num_bytes = size.div_ceil(2);
 
         // Pack the default value (e.g., 0xFF) into both nibbles
         let packed = (default << 4) | (default & 0x0f);
@@ -323,7 +825,7 @@ impl NibbleArray {
     #[inline(always)]
     pub fn get(&self, index: usize) -> u8 {
         let byte = self.data[index / 2];
-        if index % 2 == 0 {
+        if index.is_multiple_of(2) {
             // Lower nibble
             byte & 0x0f
         } else {
@@ -338,7 +840,7 @@ impl NibbleArray {
         let byte_idx = index / 2;
         let current_byte = self.data[byte_idx];
 
-        if index % 2 == 0 {
+        if index.is_multiple_of(2) {
             // Set lower nibble: Clear lower 4 bits, then OR in new value
             self.data[byte_idx] = (current_byte & 0xf0) | (value & 0x0f);
         } else {
@@ -347,3 +849,204 @@ impl NibbleArray {
         }
     }
 }
+
+impl ArchivedNibbleArray {
+    #[inline(always)]
+    pub fn get(&self, index: usize) -> u8 {
+        let byte = self.data[index / 2];
+        if index.is_multiple_of(2) { byte & 0x0f } else { (byte >> 4) & 0x0f }
+    }
+}
+
+/// Where the tests cache their tables.
+#[cfg(test)]
+pub(crate) const TEST_TABLES_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/target/pruning_tables.rkyv");
+
+/// Tables shared by all tests in the crate, loaded (or generated) once.
+#[cfg(test)]
+pub(crate) fn test_tables() -> &'static PruningTables {
+    static TABLES: std::sync::OnceLock<PruningTables> = std::sync::OnceLock::new();
+    TABLES.get_or_init(|| PruningTables::load_or_generate(TEST_TABLES_PATH))
+}
+
+/// The shared test tables with every pruning table re-encoded mod 3.
+#[cfg(test)]
+pub(crate) fn test_mod3_tables() -> &'static PruningTables {
+    static TABLES: std::sync::OnceLock<PruningTables> = std::sync::OnceLock::new();
+    TABLES.get_or_init(load_test_mod3_tables)
+}
+
+/// A fresh copy of `test_mod3_tables`, for tests that modify it.
+#[cfg(test)]
+pub(crate) fn load_test_mod3_tables() -> PruningTables {
+    let mut tables = PruningTables::load_or_generate(TEST_TABLES_PATH);
+    for table in [&mut tables.flipslice_twist_pruning, &mut tables.corner_slice_pruning, &mut tables.ud_edge_slice_pruning] {
+        let PruningArray::Nibble(distances) = table else { panic!("test tables are not nibble encoded") };
+        *table = PruningArray::Mod3(Mod3Array::from_distances(distances).unwrap());
+    }
+    tables
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generation_is_independent_of_strategy() {
+        let serial = GenerationConfig::new().threads(1);
+        let parallel = GenerationConfig::new().threads(4);
+
+        let cp_serial = MoveTable::for_coordinate::<CornerPerm>(&serial);
+        let cp_parallel = MoveTable::for_coordinate::<CornerPerm>(&parallel);
+        assert_eq!(cp_serial.data, cp_parallel.data);
+
+        let ep_slice = MoveTable::for_coordinate::<SlicePerm>(&parallel);
+        let a = pair_pruning_table::<CornerPerm, SlicePerm>(&cp_serial, &ep_slice, &Turn::PHASE2_MOVES, &serial);
+        let b = pair_pruning_table::<CornerPerm, SlicePerm>(&cp_serial, &ep_slice, &Turn::PHASE2_MOVES, &parallel);
+        assert_eq!(a.data, b.data);
+
+        // Backward from the start and forward only
+        for threshold in [0.0, 2.0] {
+            let config = GenerationConfig::new().backward_threshold(threshold);
+            let c = pair_pruning_table::<CornerPerm, SlicePerm>(&cp_serial, &ep_slice, &Turn::PHASE2_MOVES, &config);
+            assert_eq!(a.data, c.data);
+        }
+
+        // Same contents as the shared test tables
+        let PruningArray::Nibble(shared) = &test_tables().corner_slice_pruning else { panic!("test tables are not nibble encoded") };
+        assert_eq!(a.data, shared.data);
+    }
+
+    #[test]
+    fn test_generation_reports_progress() {
+        let events = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = events.clone();
+        let config = GenerationConfig::new().on_progress(move |p| sink.lock().unwrap().push(*p));
+
+        let ep_slice = MoveTable::for_coordinate::<SlicePerm>(&config);
+        pair_pruning_table::<SlicePerm, SlicePerm>(&ep_slice, &ep_slice, &Turn::PHASE2_MOVES, &config);
+
+        let events = events.lock().unwrap();
+        assert_eq!(events[0].depth, Some(0));
+        assert_eq!(events[0].filled, 1);
+        assert!(events.windows(2).all(|w| w[0].depth < w[1].depth && w[0].filled < w[1].filled));
+        let last = events.last().unwrap();
+        assert_eq!((last.table, last.filled, last.total), ("pair_pruning", 24, 576));
+    }
+
+    #[test]
+    fn test_custom_coordinate_tables() {
+        // Where the URF corner is, a coordinate the crate does not use itself
+        struct UrfPosition;
+
+        impl Coordinate for UrfPosition {
+            const COUNT: usize = 8;
+
+            fn get(cube: &CubieCube) -> usize {
+                cube.cp.iter().position(|&c| c == 0).unwrap()
+            }
+
+            fn set(value: usize) -> CubieCube {
+                let mut cube = CubieCube::SOLVED;
+                cube.cp.swap(0, value);
+                cube
+            }
+        }
+
+        let config = GenerationConfig::new();
+        let urf_move = MoveTable::for_coordinate::<UrfPosition>(&config);
+        let twist_move = MoveTable::for_coordinate::<Twist>(&config);
+        let table = pair_pruning_table::<UrfPosition, Twist>(&urf_move, &twist_move, &Turn::ALL, &config);
+
+        let index = |cube: &CubieCube| UrfPosition::get(cube) * Twist::COUNT + Twist::get(cube);
+        assert_eq!(table.get(index(&CubieCube::SOLVED)), 0);
+        assert_eq!(table.get(index(&CubieCube::R)), 1);
+        assert_eq!(table.get(index(&CubieCube::U)), 1);
+        assert!((0..table.length).all(|i| table.get(i) != 0xf));
+    }
+
+    #[test]
+    fn test_pair_table_with_moves_not_closed_under_inverses() {
+        // Both coordinates follow every turn exactly, unlike the Phase 2 slice permutation
+        let config = GenerationConfig::new();
+        let cp_move = MoveTable::for_coordinate::<CornerPerm>(&config);
+        let slice_move = MoveTable::for_coordinate::<SliceSorted>(&config);
+        let moves = [Turn::U, Turn::R];
+        let table = pair_pruning_table::<CornerPerm, SliceSorted>(&cp_move, &slice_move, &moves, &config);
+
+        // U' is undone by a single U, but U needs three more
+        let index = |cube: &CubieCube| CornerPerm::get(cube) * SliceSorted::COUNT + SliceSorted::get(cube);
+        assert_eq!(table.get(index(&Turn::U3.to_cubie())), 1);
+        assert_eq!(table.get(index(&Turn::U.to_cubie())), 3);
+
+        // Every reached entry but solved is one allowed move from an entry one closer
+        for i in (0..table.length).filter(|&i| table.get(i) != 0xf && table.get(i) != 0) {
+            let (a, b) = (i / SliceSorted::COUNT, i % SliceSorted::COUNT);
+            let next = |m: Turn| table.get(cp_move.get(a, m as usize) as usize * SliceSorted::COUNT + slice_move.get(b, m as usize) as usize);
+            assert!(moves.iter().all(|&m| next(m) + 1 >= table.get(i)));
+            assert!(moves.iter().any(|&m| next(m) + 1 == table.get(i)));
+        }
+
+        // The backward scan must not be used for such a subset
+        let backward = GenerationConfig::new().backward_threshold(0.0);
+        let same = pair_pruning_table::<CornerPerm, SliceSorted>(&cp_move, &slice_move, &moves, &backward);
+        assert_eq!(table.data, same.data);
+    }
+
+    #[test]
+    fn test_mod3_tables_give_exact_depths() {
+        use crate::coord_cube::CoordCube;
+        use rand::RngExt;
+
+        let nibble = test_tables();
+        let mod3 = test_mod3_tables();
+        assert_eq!(mod3.encoding(), PruningEncoding::Mod3);
+        assert_eq!(mod3.flipslice_twist_pruning.length(), nibble.flipslice_twist_pruning.length());
+
+        let mut rng = crate::rng::rng_from_seed(3);
+        for _ in 0..10 {
+            let mut coord = CoordCube::from_cubie(&CubieCube::random_state(&mut rng));
+            let mut depth = coord.phase1_depth(mod3).unwrap();
+            assert_eq!(depth, coord.phase1_depth(nibble).unwrap());
+
+            // Follow the distance move by move, as the solver does
+            for _ in 0..20 {
+                coord = coord.phase1_move(mod3, rng.random_range(0..18));
+                depth = coord.phase1_depth_from(mod3, depth).unwrap();
+                assert_eq!(depth, coord.phase1_depth(nibble).unwrap());
+            }
+
+            let mut g1 = CoordCube::from_cubie(&CubieCube::SOLVED);
+            for _ in 0..20 {
+                g1 = g1.phase2_move(mod3, Turn::PHASE2_MOVES[rng.random_range(0..10)] as usize);
+            }
+            let mut depths = g1.phase2_depths(mod3).unwrap();
+            assert_eq!(depths, g1.phase2_depths(nibble).unwrap());
+            for _ in 0..20 {
+                g1 = g1.phase2_move(mod3, Turn::PHASE2_MOVES[rng.random_range(0..10)] as usize);
+                depths = g1.phase2_depths_from(mod3, depths).unwrap();
+                assert_eq!(depths, g1.phase2_depths(nibble).unwrap());
+            }
+        }
+
+        // Entries beyond the generation depth limit have no distance to keep mod 3
+        let mut partial = NibbleArray::new(8, 0xf);
+        partial.set(0, 0);
+        assert!(PruningArray::encode(partial.clone(), PruningEncoding::Nibble).is_ok());
+        assert_eq!(PruningArray::encode(partial, PruningEncoding::Mod3).err(), Some(UnreachedEntry { index: 1 }));
+    }
+
+    #[test]
+    fn test_corrupt_mod3_walk_is_an_error() {
+        // States on a line towards 0, or around a ring that never reaches it
+        let walk = |residues: [u8; 4], start: usize, step: fn(usize) -> usize| {
+            exact_depth("test", PruningEncoding::Mod3, |s| s, |i| residues[i], start, |s| s == 0, &[Turn::U], |s, _| step(s))
+        };
+        let down = |s: usize| s.saturating_sub(1);
+        let ring = |s: usize| 1 + s % 3;
+
+        assert_eq!(walk([0, 1, 2, 0], 3, down), Ok(3));
+        assert_eq!(walk([0, 1, 1, 0], 3, down), Err(VerifyError::NoPredecessor { table: "test", index: 3, depth: 0 }));
+        assert!(walk([0, 0, 2, 1], 1, ring).is_err());
+    }
+}
@@ -0,0 +1,9 @@
+use rand::SeedableRng;
+use rand::rngs::ChaCha8Rng;
+
+/// Creates the RNG used wherever the crate needs reproducible randomness,
+/// such as seeded scrambles and sampled table verification.
+/// ChaCha8 is portable, so a seed gives the same sequence on every platform.
+pub fn rng_from_seed(seed: u64) -> ChaCha8Rng {
+    ChaCha8Rng::seed_from_u64(seed)
+}
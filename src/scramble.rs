@@ -0,0 +1,96 @@
+use crate::cubie_cube::CubieCube;
+use crate::pruning_table::TableLookup;
+use crate::solver::{ SolveError, Solver };
+use crate::turn::Turn;
+
+use std::fmt;
+
+use rand::Rng;
+
+pub use crate::rng::rng_from_seed;
+
+/// A scramble sequence together with the state it produces from solved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Scramble {
+    pub state: CubieCube,
+    pub moves: Vec<Turn>,
+    /// The seed the scramble was generated from, if it was seeded.
+    pub seed: Option<u64>,
+}
+
+impl fmt::Display for Scramble {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = self.moves
+            .iter()
+            .map(|m| m.to_string())
+            .collect::<Vec<_>>()
+            .join(" ");
+        write!(f, "{}", s)
+    }
+}
+
+/// Generates a random-state scramble: a uniformly sampled state and
+/// the inverse of a two-phase solution that reaches it.
+/// Fails only if the solver hits one of its configured limits.
+pub fn random_state_scramble<T, R>(solver: &mut Solver<T>, rng: &mut R) -> Result<Scramble, SolveError>
+    where T: TableLookup + ?Sized, R: Rng + ?Sized
+{
+    let state = CubieCube::random_state(rng);
+    let moves = scramble_to(solver, &state)?;
+    Ok(Scramble { state, moves, seed: None })
+}
+
+/// Generates a reproducible random-state scramble and records its seed.
+pub fn seeded_random_state_scramble<T: TableLookup + ?Sized>(
+    solver: &mut Solver<T>,
+    seed: u64
+) -> Result<Scramble, SolveError> {
+    let scramble = random_state_scramble(solver, &mut rng_from_seed(seed))?;
+    Ok(Scramble { seed: Some(seed), ..scramble })
+}
+
+/// Returns a move sequence that takes the solved cube to `state`.
+/// Fails if `state` is not a valid cube or the solver hits one of its configured limits.
+pub fn scramble_to<T: TableLookup + ?Sized>(solver: &mut Solver<T>, state: &CubieCube) -> Result<Vec<Turn>, SolveError> {
+    let solution = solver.solve(state)?;
+
+    // Undo the solution: reverse the order and invert every turn
+    Ok(solution.moves
+        .iter()
+        .rev()
+        .map(|m| m.inverse())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seeded_scrambles_are_reproducible() {
+        let mut a = CubieCube::SOLVED;
+        let mut b = CubieCube::SOLVED;
+        let moves_a = a.scramble(25, &mut rng_from_seed(7));
+        let moves_b = b.scramble(25, &mut rng_from_seed(7));
+        assert_eq!(moves_a, moves_b);
+        assert_eq!(a, b);
+
+        let state_a = CubieCube::random_state(&mut rng_from_seed(42));
+        let state_b = CubieCube::random_state(&mut rng_from_seed(42));
+        assert_eq!(state_a, state_b);
+    }
+
+    #[test]
+    fn test_seeded_scrambles_are_portable() {
+        // Pinned outputs: if these change, previously published scramble sets can no longer be replayed
+        let mut cube = CubieCube::SOLVED;
+        let moves = cube.scramble(10, &mut rng_from_seed(7));
+        assert_eq!(
+            moves,
+            vec![Turn::U3, Turn::R, Turn::U, Turn::L2, Turn::U2, Turn::D2, Turn::L3, Turn::F, Turn::R2, Turn::U2]
+        );
+
+        let state = CubieCube::random_state(&mut rng_from_seed(42));
+        assert_eq!(state.to_facelet_string(), "UUUFURLDUFBFFRRBUFBRLBFLBURLFDLDLRDLBDURLLFDDRBRUBFDBD");
+    }
+}
@@ -1,117 +1,525 @@
+use crate::coord_cube::CoordCube;
 use crate::cubie_cube::CubieCube;
-use crate::pruning_table::PruningTables;
+use crate::pruning_table::{ PruningTables, TableLookup };
+use crate::table_stats::VerifyError;
 use crate::turn::Turn;
+use crate::validation::ValidationError;
 
-pub struct Solver<'a> {
-    tables: &'a PruningTables,
-    max_length: u8,
+use std::fmt;
+use std::ops::ControlFlow;
+use std::sync::Arc;
+use std::sync::atomic::{ AtomicBool, Ordering };
+use std::time::{ Duration, Instant };
+
+/// A shared flag that stops a running solve from another thread.
+///
+/// Clones share the flag. A cancelled token stays cancelled, so use a fresh one per solve.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes every solve using this token return `SolveError::Cancelled` at its next check.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// The state of a running solve, as reported to `SolverConfig::on_progress`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SolveProgress {
+    /// Phase 1 depth being searched.
+    pub phase1_depth: u8,
+    /// Length of the best solution so far, if there is one.
+    pub best_length: Option<u8>,
+    /// Nodes visited in both phases.
+    pub nodes: u64,
+    /// Time since the solve started.
+    pub elapsed: Duration,
+}
+
+type ProgressObserver = Arc<dyn Fn(&SolveProgress) + Send + Sync>;
+
+/// Limits for a single `Solver::solve` call.
+#[derive(Clone)]
+pub struct SolverConfig {
+    /// Stop searching as soon as a solution this short is found.
+    pub target_length: u8,
+    /// Never return a solution longer than this.
+    pub max_length: u8,
+    /// Give up after this long and return the best solution so far.
+    pub timeout: Option<Duration>,
+    /// Give up after visiting this many search nodes and return the best solution so far.
+    pub node_budget: Option<u64>,
+    /// Deepest Phase 1 search (no state needs more than 12 moves to reach G1).
+    pub max_phase1_depth: u8,
+    /// Stops the search when cancelled, returning the best solution so far.
+    pub cancel: Option<CancelToken>,
+    observer: Option<ProgressObserver>,
 }
 
-impl<'a> Solver<'a> {
-    pub fn new(tables: &'a PruningTables) -> Self {
+impl Default for SolverConfig {
+    fn default() -> Self {
         Self {
-            tables,
+            target_length: 21,
             max_length: 22,
+            timeout: None,
+            node_budget: None,
+            max_phase1_depth: 12,
+            cancel: None,
+            observer: None,
+        }
+    }
+}
+
+impl fmt::Debug for SolverConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SolverConfig")
+            .field("target_length", &self.target_length)
+            .field("max_length", &self.max_length)
+            .field("timeout", &self.timeout)
+            .field("node_budget", &self.node_budget)
+            .field("max_phase1_depth", &self.max_phase1_depth)
+            .field("cancel", &self.cancel)
+            .field("observer", &self.observer.as_ref().map(|_| "Fn"))
+            .finish()
+    }
+}
+
+impl SolverConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stop at the first solution of at most `length` moves. 0 keeps searching until the
+    /// Phase 1 depth limit, giving the shortest solution the two-phase search can find.
+    pub fn target_length(mut self, length: u8) -> Self {
+        self.target_length = length;
+        self
+    }
+
+    pub fn max_length(mut self, length: u8) -> Self {
+        self.max_length = length;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn node_budget(mut self, nodes: u64) -> Self {
+        self.node_budget = Some(nodes);
+        self
+    }
+
+    pub fn max_phase1_depth(mut self, depth: u8) -> Self {
+        self.max_phase1_depth = depth;
+        self
+    }
+
+    /// Checks `token` regularly during the search (every few thousand nodes).
+    pub fn cancel_token(mut self, token: CancelToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// Calls `observer` whenever a new Phase 1 depth starts and every `PROGRESS_INTERVAL`
+    /// nodes in between. The search is silent without one.
+    pub fn on_progress(mut self, observer: impl Fn(&SolveProgress) + Send + Sync + 'static) -> Self {
+        self.observer = Some(Arc::new(observer));
+        self
+    }
+}
+
+/// How much searching a `solve` call did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SolveStats {
+    pub phase1_nodes: u64,
+    pub phase2_nodes: u64,
+    pub elapsed: Duration,
+}
+
+/// A move sequence that solves the cube, split into its two phases.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Solution {
+    pub moves: Vec<Turn>,
+    /// `moves[..phase1_length]` reaches G1, the rest solves the cube within G1.
+    pub phase1_length: usize,
+    /// Statistics of the search up to the point the solution was reported or returned.
+    pub stats: SolveStats,
+}
+
+impl Solution {
+    pub fn phase1(&self) -> &[Turn] {
+        &self.moves[..self.phase1_length]
+    }
+
+    pub fn phase2(&self) -> &[Turn] {
+        &self.moves[self.phase1_length..]
+    }
+
+    /// Length in Half-Turn Metric.
+    pub fn htm(&self) -> usize {
+        self.moves.len()
+    }
+
+    /// Length in Quarter-Turn Metric.
+    pub fn qtm(&self) -> usize {
+        self.moves
+            .iter()
+            .map(|m| m.quarter_turns() as usize)
+            .sum()
+    }
+}
+
+impl fmt::Display for Solution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = self.moves
+            .iter()
+            .map(|m| m.to_string())
+            .collect::<Vec<_>>()
+            .join(" ");
+        write!(f, "{}", s)
+    }
+}
+
+/// Why `solve` did not return a solution.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SolveError {
+    /// The cube is not a reachable state, so no sequence of turns solves it.
+    InvalidCube(ValidationError),
+    /// The timeout ran out before the target length was reached.
+    /// `best` is the shortest solution found until then.
+    TimedOut {
+        best: Option<Solution>,
+    },
+    /// The node budget ran out before the target length was reached.
+    /// `best` is the shortest solution found until then.
+    BudgetExhausted {
+        best: Option<Solution>,
+    },
+    /// The `CancelToken` was cancelled. `best` is the shortest solution found until then.
+    Cancelled {
+        best: Option<Solution>,
+    },
+    /// The whole search up to the Phase 1 depth limit found nothing within `max_length`.
+    NotFound,
+    /// A pruning table gave a distance that cannot be right, so the tables are corrupt.
+    CorruptTable(VerifyError),
+}
+
+impl SolveError {
+    /// The best solution found before a limit was hit, if any.
+    pub fn best(&self) -> Option<&Solution> {
+        match self {
+            SolveError::TimedOut { best } | SolveError::BudgetExhausted { best } | SolveError::Cancelled { best } => {
+                best.as_ref()
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for SolveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let found = |best: &Option<Solution>| {
+            best.as_ref().map_or("no solution found".to_string(), |s| format!("best has {} moves", s.htm()))
+        };
+        match self {
+            SolveError::InvalidCube(e) => write!(f, "cube cannot be solved: {}", e),
+            SolveError::TimedOut { best } => write!(f, "search timed out, {}", found(best)),
+            SolveError::BudgetExhausted { best } => write!(f, "node budget exhausted, {}", found(best)),
+            SolveError::Cancelled { best } => write!(f, "search cancelled, {}", found(best)),
+            SolveError::NotFound => write!(f, "no solution within the length and depth limits"),
+            SolveError::CorruptTable(e) => write!(f, "pruning tables are corrupt: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SolveError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SolveError::InvalidCube(e) => Some(e),
+            SolveError::CorruptTable(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Which limit ended a search early.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Stop {
+    Timeout,
+    Budget,
+    Cancelled,
+    CorruptTable(VerifyError),
+}
+
+// How many nodes to visit between looking at the clock and the cancel token
+const CHECK_INTERVAL: u64 = 1024;
+
+/// Nodes between two progress reports within a Phase 1 depth (about 50ms of searching).
+pub const PROGRESS_INTERVAL: u64 = 1 << 20;
+
+/// Everything a search updates while it runs.
+struct SearchState<'f> {
+    best_solution: Option<Solution>,
+    best_length: u8,
+    target_length: u8,
+    on_solution: &'f mut dyn FnMut(&Solution) -> ControlFlow<()>,
+    // Set once the best solution is good enough for the target or the callback
+    done: bool,
+    // Nodes visited in Phase 1 and Phase 2
+    nodes: [u64; 2],
+    started: Instant,
+    deadline: Option<Instant>,
+    node_budget: Option<u64>,
+    cancel: Option<&'f CancelToken>,
+    observer: Option<&'f (dyn Fn(&SolveProgress) + Send + Sync)>,
+    phase1_depth: u8,
+    stopped: Option<Stop>,
+}
+
+impl SearchState<'_> {
+    fn stats(&self) -> SolveStats {
+        SolveStats {
+            phase1_nodes: self.nodes[0],
+            phase2_nodes: self.nodes[1],
+            elapsed: self.started.elapsed(),
         }
     }
 
-    pub fn solve(&mut self, cube: &CubieCube) -> Option<String> {
-        let mut best_solution: Option<Vec<Turn>> = None;
-        let mut best_length = self.max_length + 1;
+    /// Keeps a new best solution and reports it.
+    fn improve(&mut self, moves: Vec<Turn>, phase1_length: u8) {
+        let solution = Solution {
+            moves,
+            phase1_length: phase1_length as usize,
+            stats: self.stats(),
+        };
+        self.best_length = solution.htm() as u8;
+        let stop = (self.on_solution)(&solution).is_break();
+        self.done = stop || self.best_length <= self.target_length;
+        self.best_solution = Some(solution);
+    }
+
+    /// Whether the search should unwind without looking any further.
+    fn finished(&self) -> bool {
+        self.done || self.stopped.is_some()
+    }
 
-        // println!("--- Starting Two-Phase Search ---");
+    /// Counts a node of the given phase (0 or 1) and returns whether the search has to stop.
+    #[inline(always)]
+    fn visit(&mut self, phase: usize) -> bool {
+        self.nodes[phase] += 1;
+        let total = self.nodes[0] + self.nodes[1];
+        if self.node_budget.is_some_and(|budget| total > budget) {
+            self.stopped = Some(Stop::Budget);
+        }
+        if total.is_multiple_of(CHECK_INTERVAL) {
+            self.check();
+            if total.is_multiple_of(PROGRESS_INTERVAL) {
+                self.report();
+            }
+        }
+        self.stopped.is_some()
+    }
+
+    /// Looks at the cancel token and the clock.
+    fn check(&mut self) {
+        if self.cancel.is_some_and(|c| c.is_cancelled()) {
+            self.stopped = Some(Stop::Cancelled);
+        } else if self.deadline.is_some_and(|d| Instant::now() >= d) {
+            self.stopped = Some(Stop::Timeout);
+        }
+    }
+
+    fn report(&self) {
+        if let Some(observer) = self.observer {
+            observer(
+                &(SolveProgress {
+                    phase1_depth: self.phase1_depth,
+                    best_length: self.best_solution.as_ref().map(|s| s.htm() as u8),
+                    nodes: self.nodes[0] + self.nodes[1],
+                    elapsed: self.started.elapsed(),
+                })
+            );
+        }
+    }
+}
 
-        // Phase 1 Iterative Deepening (0 to 12 moves)
-        for p1_bound in 0..=12 {
+/// Two-phase solver over any table source: owned `PruningTables`,
+/// or `ArchivedPruningTables` from a memory-mapped cache.
+pub struct Solver<'a, T: TableLookup + ?Sized = PruningTables> {
+    tables: &'a T,
+    config: SolverConfig,
+    // The 18 turns as cubies, only needed to enter Phase 2
+    move_cubes: [CubieCube; 18],
+}
+
+impl<'a, T: TableLookup + ?Sized> Solver<'a, T> {
+    pub fn new(tables: &'a T) -> Self {
+        Self::with_config(tables, SolverConfig::default())
+    }
+
+    pub fn with_config(tables: &'a T, config: SolverConfig) -> Self {
+        Self {
+            tables,
+            config,
+            move_cubes: Turn::ALL.map(|m| m.to_cubie()),
+        }
+    }
+
+    pub fn config(&self) -> &SolverConfig {
+        &self.config
+    }
+
+    /// Searches for a solution within the configured limits.
+    ///
+    /// Returns the best solution once it is at most `target_length` moves long, or once the
+    /// search up to `max_phase1_depth` is exhausted. When the timeout or node budget ends the
+    /// search first, the error carries the best solution found so far.
+    pub fn solve(&mut self, cube: &CubieCube) -> Result<Solution, SolveError> {
+        self.solve_with(cube, |_| ControlFlow::Continue(()))
+    }
+
+    /// Like `solve`, but calls `on_solution` with every solution that is strictly shorter
+    /// than the ones before, as soon as it is found.
+    ///
+    /// Returning `ControlFlow::Break` from the callback ends the search and returns that
+    /// solution. The search also stops at `target_length` as in `solve`, so set it to 0 to
+    /// let the callback alone decide.
+    pub fn solve_with(
+        &mut self,
+        cube: &CubieCube,
+        mut on_solution: impl FnMut(&Solution) -> ControlFlow<()>
+    ) -> Result<Solution, SolveError> {
+        cube.validate().map_err(SolveError::InvalidCube)?;
+
+        let started = Instant::now();
+        let mut state = SearchState {
+            best_solution: None,
+            best_length: self.config.max_length.saturating_add(1),
+            target_length: self.config.target_length,
+            on_solution: &mut on_solution,
+            done: false,
+            nodes: [0; 2],
+            started,
+            deadline: self.config.timeout.map(|t| started + t),
+            node_budget: self.config.node_budget,
+            cancel: self.config.cancel.as_ref(),
+            observer: self.config.observer.as_deref(),
+            phase1_depth: 0,
+            stopped: None,
+        };
+
+        // The exact distance is only looked up once, the searches derive it from move to move
+        let coord = CoordCube::from_cubie(cube);
+        let h1 = coord.phase1_depth(self.tables).map_err(SolveError::CorruptTable)?;
+
+        // Phase 1 Iterative Deepening (0 to 12 moves by default)
+        for p1_bound in 0..=self.config.max_phase1_depth {
             // THE FIX: Stop outer loop if Phase 1 alone is worse than our best total solve
-            if p1_bound >= best_length {
-                // println!(
-                //     "Phase 1 bound ({}) exceeded best length ({}). Search complete.",
-                //     p1_bound,
-                //     best_length
-                // );
+            if p1_bound >= state.best_length {
                 break;
             }
 
-            if best_length <= 21 {
-                // println!("Found a highly optimal solution ({} moves). Skipping deep exhaustive search.", best_length);
+            if state.done {
                 break;
             }
 
-            // MONITORING: Show the current search depth
-            // println!("Searching Phase 1 Depth: {} (Current best: {})", p1_bound, if
-            //     best_length > 22
-            // {
-            //     "None".to_string()
-            // } else {
-            //     best_length.to_string()
-            // });
+            // Check the limits and report progress before each Phase 1 depth
+            state.phase1_depth = p1_bound;
+            state.check();
+            if state.stopped.is_some() {
+                break;
+            }
+            state.report();
 
             let mut path = Vec::new();
-            self.phase1_search(cube, 0, p1_bound, &mut path, &mut best_solution, &mut best_length);
-        }
+            self.phase1_search(cube, coord, h1, 0, p1_bound, &mut path, &mut state);
 
-        // println!("--- Search Finished ---");
-        best_solution.map(|s| self.format_solution(&s))
-    }
+            if state.stopped.is_some() {
+                break;
+            }
+        }
 
-    fn format_solution(&self, moves: &[Turn]) -> String {
-        moves
-            .iter()
-            .map(|m| m.to_string())
-            .collect::<Vec<_>>()
-            .join(" ")
+        let stats = state.stats();
+        let best = state.best_solution.map(|s| Solution { stats, ..s });
+        match (state.stopped, best) {
+            (Some(Stop::Timeout), best) => Err(SolveError::TimedOut { best }),
+            (Some(Stop::Budget), best) => Err(SolveError::BudgetExhausted { best }),
+            (Some(Stop::Cancelled), best) => Err(SolveError::Cancelled { best }),
+            (Some(Stop::CorruptTable(e)), _) => Err(SolveError::CorruptTable(e)),
+            (None, Some(best)) => Ok(best),
+            (None, None) => Err(SolveError::NotFound),
+        }
     }
 }
 
-impl<'a> Solver<'a> {
+impl<'a, T: TableLookup + ?Sized> Solver<'a, T> {
+    #[allow(clippy::too_many_arguments)]
     fn phase1_search(
         &self,
-        cube: &CubieCube,
+        start: &CubieCube,
+        coord: CoordCube,
+        h1: u8,
         g: u8,
         p1_bound: u8,
         path: &mut Vec<Turn>,
-        best_solution: &mut Option<Vec<Turn>>,
-        best_length: &mut u8
+        state: &mut SearchState
     ) {
-        let twist = cube.get_twist() as usize;
-        let flip = cube.get_flip() as usize;
-        let slice = cube.get_slice_sorted() as usize;
-
-        let h1 = std::cmp::max(
-            self.tables.twist_slice_pruning.get(twist * 495 + slice),
-            self.tables.flip_slice_pruning.get(flip * 495 + slice)
-        );
+        if state.visit(0) {
+            return;
+        }
 
         // Standard Pruning and Global Bound Pruning
         // If this branch mathematically cannot beat our best solution, kill it instantly.
-        if g + h1 > p1_bound || g + h1 >= *best_length {
+        if g + h1 > p1_bound || g + h1 >= state.best_length {
             return;
         }
 
         // Reached the G1 subgroup at exactly the target Phase 1 depth
         if h1 == 0 && g == p1_bound {
+            // Phase 2 needs the permutation coordinates, which the Phase 1 coordinates
+            // do not track. Rebuild the cubie state once here instead of at every node.
+            let mut cube = *start;
+            for &m in path.iter() {
+                cube = cube.multiply(&self.move_cubes[m as usize]);
+            }
+            let p2_coord = CoordCube::from_cubie(&cube);
+            let p2_depths = match p2_coord.phase2_depths(self.tables) {
+                Ok(depths) => depths,
+                Err(e) => {
+                    state.stopped = Some(Stop::CorruptTable(e));
+                    return;
+                }
+            };
+
             // Strictly limit Phase 2 to ensure we only find paths SHORTER than our best
-            let max_p2 = *best_length - g - 1;
+            let max_p2 = state.best_length - g - 1;
 
             for p2_bound in 0..=max_p2 {
                 let mut p2_path = path.clone();
-                if self.phase2_search(cube, 0, p2_bound, &mut p2_path) {
+                if self.phase2_search(p2_coord, p2_depths, 0, p2_bound, &mut p2_path, state) {
                     let total_length = g + p2_bound;
 
-                    if total_length < *best_length {
-                        *best_length = total_length;
-                        *best_solution = Some(p2_path.clone());
-
-                        // println!(
-                        //     "  -> Found better solution! Length: {:02} | Moves: {}",
-                        //     total_length,
-                        //     self.format_solution(&p2_path)
-                        // );
+                    if total_length < state.best_length {
+                        state.improve(p2_path, g);
                     }
 
                     break;
                 }
+                if state.stopped.is_some() {
+                    return;
+                }
             }
         }
 
@@ -127,28 +535,44 @@ impl<'a> Solver<'a> {
                 continue;
             }
 
-            let next_cube = cube.multiply(&m.to_cubie());
+            let next_coord = coord.phase1_move(self.tables, m as usize);
+            let next_h1 = match next_coord.phase1_depth_from(self.tables, h1) {
+                Ok(depth) => depth,
+                Err(e) => {
+                    state.stopped = Some(Stop::CorruptTable(e));
+                    return;
+                }
+            };
             path.push(m);
-            self.phase1_search(&next_cube, g + 1, p1_bound, path, best_solution, best_length);
+            self.phase1_search(start, next_coord, next_h1, g + 1, p1_bound, path, state);
             path.pop();
+
+            if state.finished() {
+                return;
+            }
         }
     }
 
-    fn phase2_search(&self, cube: &CubieCube, g: u8, p2_bound: u8, path: &mut Vec<Turn>) -> bool {
-        let cp = cube.get_corner_perm();
-        let ud = cube.get_ud_edges();
-        let slice = cube.get_slice_perm();
+    fn phase2_search(
+        &self,
+        coord: CoordCube,
+        depths: [u8; 2],
+        g: u8,
+        p2_bound: u8,
+        path: &mut Vec<Turn>,
+        state: &mut SearchState
+    ) -> bool {
+        if state.visit(1) {
+            return false;
+        }
 
-        let h2 = std::cmp::max(
-            self.tables.corner_slice_pruning.get(cp * 24 + slice),
-            self.tables.ud_edge_slice_pruning.get(ud * 24 + slice)
-        );
+        let h2 = std::cmp::max(depths[0], depths[1]);
 
         if g + h2 > p2_bound {
             return false;
         }
 
-        if h2 == 0 && cp == 0 && ud == 0 && slice == 0 {
+        if h2 == 0 && coord.is_phase2_solved() {
             return g == p2_bound;
         }
 
@@ -163,14 +587,211 @@ impl<'a> Solver<'a> {
                 continue;
             }
 
-            let next_cube = cube.multiply(&m.to_cubie());
+            let next_coord = coord.phase2_move(self.tables, m as usize);
+            let next_depths = match next_coord.phase2_depths_from(self.tables, depths) {
+                Ok(depths) => depths,
+                Err(e) => {
+                    state.stopped = Some(Stop::CorruptTable(e));
+                    return false;
+                }
+            };
             path.push(m);
-            if self.phase2_search(&next_cube, g + 1, p2_bound, path) {
+            if self.phase2_search(next_coord, next_depths, g + 1, p2_bound, path, state) {
                 return true;
             }
             path.pop();
+
+            if state.stopped.is_some() {
+                return false;
+            }
         }
 
         false
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generation::SearchSpace;
+    use crate::pruning_table::{ load_test_mod3_tables, test_mod3_tables, test_tables, FlipSliceTwistSpace, PruningArray };
+
+    fn assert_solves(scramble: &str) {
+        assert_solves_with(test_tables(), scramble);
+    }
+
+    fn assert_solves_with(tables: &PruningTables, scramble: &str) {
+        let mut cube = CubieCube::SOLVED;
+        cube.apply_sequence(scramble).unwrap();
+
+        let mut solver = Solver::new(tables);
+        let solution = solver.solve(&cube).expect("no solution found");
+        assert!(solution.htm() <= 22);
+        assert!(solution.qtm() >= solution.htm());
+
+        // Phase 1 has to end in G1: no twist, no flip and the slice edges in the slice
+        cube.apply_moves(solution.phase1());
+        let g1 = CoordCube::from_cubie(&cube);
+        assert_eq!((g1.twist, g1.flip, g1.phase1_depth(tables).unwrap()), (0, 0, 0));
+
+        cube.apply_moves(solution.phase2());
+        assert_eq!(cube, CubieCube::SOLVED, "{} does not solve {}", solution, scramble);
+    }
+
+    #[test]
+    fn test_solves_scrambles() {
+        assert_solves("");
+        assert_solves("R U R' U' R");
+        assert_solves("L' U' R' U D2 F' B L2 B2 R F' D2 R' D B2 R U' L D' R2");
+        assert_solves("D2 R2 F2 D2 F2 U2 R2 F2 U R2 D2 R B' U' L' F' L2 R' B' F2");
+    }
+
+    #[test]
+    fn test_solves_with_mod3_tables() {
+        assert_solves_with(test_mod3_tables(), "R U R' U' R");
+        assert_solves_with(test_mod3_tables(), "L' U' R' U D2 F' B L2 B2 R F' D2 R' D B2 R U' L D' R2");
+    }
+
+    #[test]
+    fn test_config_limits() {
+        let scramble = "L' U' R' U D2 F' B L2 B2 R F' D2 R' D B2 R U' L D' R2";
+        let mut cube = CubieCube::SOLVED;
+        cube.apply_sequence(scramble).unwrap();
+
+        // A stricter target keeps searching past the first 21-move solution
+        let config = SolverConfig::new().target_length(20).timeout(Duration::from_secs(30));
+        let solution = Solver::with_config(test_tables(), config).solve(&cube).expect("no solution found");
+        assert!(solution.htm() <= 20, "{} is longer than 20 moves", solution);
+
+        // Out of budget before anything was found
+        let config = SolverConfig::new().node_budget(10);
+        let result = Solver::with_config(test_tables(), config).solve(&cube);
+        assert_eq!(result, Err(SolveError::BudgetExhausted { best: None }));
+
+        // Nothing longer than max_length is returned
+        let config = SolverConfig::new().max_length(5);
+        assert_eq!(Solver::with_config(test_tables(), config).solve(&cube), Err(SolveError::NotFound));
+
+        // No limit at all
+        let config = SolverConfig::new().max_length(u8::MAX);
+        assert!(Solver::with_config(test_tables(), config).solve(&cube).is_ok());
+    }
+
+    #[test]
+    fn test_limits_keep_best_solution() {
+        let mut cube = CubieCube::SOLVED;
+        cube.apply_sequence("D2 R2 F2 D2 F2 U2 R2 F2 U R2 D2 R B' U' L' F' L2 R' B' F2").unwrap();
+
+        // Asking for an impossible length runs into the budget with a solution already found
+        let config = SolverConfig::new().target_length(0).node_budget(100_000);
+        let result = Solver::with_config(test_tables(), config).solve(&cube);
+        let Err(SolveError::BudgetExhausted { best: Some(best) }) = result else {
+            panic!("expected the budget to run out after a solution was found");
+        };
+        assert!(best.stats.phase1_nodes + best.stats.phase2_nodes > 100_000);
+        cube.apply_moves(&best.moves);
+        assert_eq!(cube, CubieCube::SOLVED);
+    }
+
+    #[test]
+    fn test_reports_shorter_solutions() {
+        let mut cube = CubieCube::SOLVED;
+        cube.apply_sequence("L' U' R' U D2 F' B L2 B2 R F' D2 R' D B2 R U' L D' R2").unwrap();
+
+        let mut solver = Solver::with_config(test_tables(), SolverConfig::new().target_length(0));
+        let mut lengths = Vec::new();
+        let best = solver
+            .solve_with(&cube, |s| {
+                lengths.push(s.htm());
+                ControlFlow::Continue(())
+            })
+            .unwrap();
+        assert!(lengths.len() > 1);
+        assert!(lengths.windows(2).all(|w| w[1] < w[0]), "{:?} is not strictly decreasing", lengths);
+        assert_eq!(*lengths.last().unwrap(), best.htm());
+
+        // Breaking returns the solution at hand
+        let first = solver.solve_with(&cube, |_| ControlFlow::Break(())).unwrap();
+        assert_eq!(first.htm(), lengths[0]);
+    }
+
+    #[test]
+    fn test_cancel_and_progress() {
+        use std::sync::Mutex;
+
+        let mut cube = CubieCube::SOLVED;
+        cube.apply_sequence("L' U' R' U D2 F' B L2 B2 R F' D2 R' D B2 R U' L D' R2").unwrap();
+
+        let token = CancelToken::new();
+        token.cancel();
+        let result = Solver::with_config(test_tables(), SolverConfig::new().cancel_token(token)).solve(&cube);
+        assert_eq!(result, Err(SolveError::Cancelled { best: None }));
+
+        // Search well past the usual Phase 1 limit and cancel from the progress callback
+        // once a solution is known
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let token = CancelToken::new();
+        let config = SolverConfig::new()
+            .target_length(0)
+            .max_phase1_depth(20)
+            .cancel_token(token.clone())
+            .on_progress({
+                let events = events.clone();
+                move |p| {
+                    events.lock().unwrap().push(*p);
+                    if p.best_length.is_some() && p.nodes >= PROGRESS_INTERVAL {
+                        token.cancel();
+                    }
+                }
+            });
+        let result = Solver::with_config(test_tables(), config).solve(&cube);
+        assert!(matches!(result, Err(SolveError::Cancelled { best: Some(_) })), "{:?}", result);
+
+        let events = events.lock().unwrap();
+        assert_eq!(events[0].phase1_depth, 0);
+        assert!(events.windows(2).all(|w| w[0].phase1_depth <= w[1].phase1_depth && w[0].nodes <= w[1].nodes));
+        assert!(events.last().unwrap().best_length.is_some());
+    }
+
+    #[test]
+    fn test_corrupt_mod3_step_is_an_error() {
+        // A state one move out of G1, stored as if it were one move closer than G1
+        let mut tables = load_test_mod3_tables();
+        let space = FlipSliceTwistSpace::new(
+            &tables.twist_move,
+            &tables.flip_move,
+            &tables.slice_move,
+            &tables.flipslice_classes,
+            &tables.twist_conj[..]
+        );
+        let mut index = 0;
+        assert!(space.any_neighbour(space.solved_index(), |n| {
+            index = n;
+            tables.flipslice_twist_pruning.get(n) == 1
+        }));
+        let PruningArray::Mod3(phase1) = &mut tables.flipslice_twist_pruning else { unreachable!() };
+        phase1.data[index / 4] ^= 0b11 << ((index % 4) * 2);
+
+        // Already in G1, so only the step into that state reads it
+        let mut cube = CubieCube::SOLVED;
+        cube.apply_sequence("U R2 F2 D L2 B2 U2 F2 R2 D' L2").unwrap();
+        let config = SolverConfig::new().target_length(0);
+        let result = Solver::with_config(&tables, config).solve(&cube);
+        assert!(matches!(result, Err(SolveError::CorruptTable(_))), "{:?}", result);
+
+        // The same in Phase 2, one move away from solved
+        let PruningArray::Mod3(corners) = &mut tables.corner_slice_pruning else { unreachable!() };
+        let index = 24 * tables.cp_move.get(0, Turn::U as usize) as usize + tables.ep_slice_move.get(0, Turn::U as usize) as usize;
+        corners.data[index / 4] ^= 0b11 << ((index % 4) * 2);
+        let coord = CoordCube::from_cubie(&Turn::U.to_cubie());
+        assert!(coord.phase2_depths_from(&tables, [0, 1]).is_err());
+    }
+
+    #[test]
+    fn test_rejects_invalid_cube() {
+        let mut cube = CubieCube::SOLVED;
+        cube.co[0] = 1;
+        let result = Solver::new(test_tables()).solve(&cube);
+        assert!(matches!(result, Err(SolveError::InvalidCube(ValidationError::TwistedCorner { .. }))));
+    }
+}
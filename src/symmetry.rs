@@ -0,0 +1,271 @@
+use crate::cubie_cube::CubieCube;
+use crate::turn::Turn;
+
+use std::sync::OnceLock;
+
+use rkyv::{ Archive, Deserialize, Serialize };
+
+/// Number of cube symmetries (24 rotations, each with or without a reflection)
+pub const N_SYM: usize = 48;
+
+/// Number of symmetries that keep the UD axis in place (the D4h subgroup).
+/// These are the first 16 entries of the symmetry table.
+pub const N_SYM_D4H: usize = 16;
+
+/// Number of flip-slice equivalence classes under the 16 D4h symmetries
+pub const N_FLIPSLICE_CLASS: usize = 64430;
+
+// Flip (2048) * Slice (495) combined coordinate: slice * 2048 + flip
+const N_FLIPSLICE: usize = 2048 * 495;
+
+// 120° clockwise rotation around the long diagonal URF-DBL
+const ROT_URF3: CubieCube = CubieCube {
+    cp: [0, 4, 5, 1, 3, 7, 6, 2],
+    co: [1, 2, 1, 2, 2, 1, 2, 1],
+    ep: [1, 8, 5, 9, 3, 11, 7, 10, 0, 4, 6, 2],
+    eo: [1, 0, 1, 0, 1, 0, 1, 0, 1, 1, 1, 1],
+};
+
+// 180° rotation around the axis through the F and B centres
+const ROT_F2: CubieCube = CubieCube {
+    cp: [5, 4, 7, 6, 1, 0, 3, 2],
+    co: [0; 8],
+    ep: [6, 5, 4, 7, 2, 1, 0, 3, 9, 8, 11, 10],
+    eo: [0; 12],
+};
+
+// 90° clockwise rotation around the axis through the U and D centres
+const ROT_U4: CubieCube = CubieCube {
+    cp: [3, 0, 1, 2, 7, 4, 5, 6],
+    co: [0; 8],
+    ep: [3, 0, 1, 2, 7, 4, 5, 6, 11, 8, 9, 10],
+    eo: [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1],
+};
+
+// Reflection at the plane through the U, D, F and B centres.
+// Corner orientations 3..5 mark a mirrored corner.
+const MIRR_LR2: CubieCube = CubieCube {
+    cp: [1, 0, 3, 2, 5, 4, 7, 6],
+    co: [3; 8],
+    ep: [2, 1, 0, 3, 6, 5, 4, 7, 9, 8, 11, 10],
+    eo: [0; 12],
+};
+
+/// The 48 symmetries of the cube and how they act on turns.
+///
+/// Symmetry `s` is `ROT_URF3^a * ROT_F2^b * ROT_U4^c * MIRR_LR2^d` with
+/// `s = 16a + 8b + 2c + d`, so the symmetries with `s < 16` keep the UD axis fixed.
+pub struct Symmetries {
+    /// Each symmetry as a cube state. Reflected symmetries have corner orientations 3..5.
+    pub cubes: [CubieCube; N_SYM],
+    /// `inverse[s]` is the symmetry that undoes `s`.
+    pub inverse: [u8; N_SYM],
+    /// `turns[s][m]` is the turn `S * m * S^-1`, i.e. `m` relabelled by symmetry `s`.
+    pub turns: [[Turn; 18]; N_SYM],
+}
+
+impl Symmetries {
+    fn generate() -> Self {
+        let mut cubes = [CubieCube::SOLVED; N_SYM];
+        let mut cc = CubieCube::SOLVED;
+
+        for urf3 in 0..3 {
+            for f2 in 0..2 {
+                for u4 in 0..4 {
+                    for lr2 in 0..2 {
+                        cubes[16 * urf3 + 8 * f2 + 2 * u4 + lr2] = cc;
+                        cc = cc.multiply(&MIRR_LR2);
+                    }
+                    cc = cc.multiply(&ROT_U4);
+                }
+                cc = cc.multiply(&ROT_F2);
+            }
+            cc = cc.multiply(&ROT_URF3);
+        }
+
+        let mut inverse = [0; N_SYM];
+        for (i, inv) in inverse.iter_mut().enumerate() {
+            *inv = (0..N_SYM)
+                .find(|&j| cubes[i].multiply(&cubes[j]) == CubieCube::SOLVED)
+                .expect("every symmetry has an inverse") as u8;
+        }
+
+        let move_cubes: Vec<CubieCube> = Turn::ALL.iter()
+            .map(|m| m.to_cubie())
+            .collect();
+
+        let mut turns = [[Turn::U; 18]; N_SYM];
+        for (s, row) in turns.iter_mut().enumerate() {
+            let s_inv = &cubes[inverse[s] as usize];
+            for (m_idx, m_cubie) in move_cubes.iter().enumerate() {
+                let conj = cubes[s].multiply(m_cubie).multiply(s_inv);
+                let target = move_cubes
+                    .iter()
+                    .position(|c| *c == conj)
+                    .expect("a symmetry maps every turn to a turn");
+                row[m_idx] = Turn::ALL[target];
+            }
+        }
+
+        Self { cubes, inverse, turns }
+    }
+}
+
+/// Returns the shared symmetry tables, building them on first use.
+pub fn symmetries() -> &'static Symmetries {
+    static SYMMETRIES: OnceLock<Symmetries> = OnceLock::new();
+    SYMMETRIES.get_or_init(Symmetries::generate)
+}
+
+/// Reduces the flip-slice coordinate to its D4h symmetry class.
+///
+/// A flip-slice index `fs = slice * 2048 + flip` belongs to class `classidx[fs]`, and
+/// conjugating it by symmetry `sym[fs]` (`S * cube * S^-1`) gives the class representative.
+#[derive(Archive, Serialize, Deserialize)]
+pub struct FlipSliceClasses {
+    pub classidx: Vec<u16>, // [495 * 2048]
+    pub sym: Vec<u8>, // [495 * 2048]
+    pub rep: Vec<u32>, // [64430] flip-slice index of each representative
+    pub rep_sym: Vec<u16>, // [64430] bitmask of the symmetries that leave the representative unchanged
+}
+
+impl FlipSliceClasses {
+    pub fn generate() -> Self {
+        let syms = symmetries();
+        let mut classidx = vec![u16::MAX; N_FLIPSLICE];
+        let mut sym = vec![0; N_FLIPSLICE];
+        let mut rep = Vec::with_capacity(N_FLIPSLICE_CLASS);
+        let mut rep_sym = Vec::with_capacity(N_FLIPSLICE_CLASS);
+
+        for slice in 0..495 {
+            let slice_cube = CubieCube::set_slice_sorted(slice as u16);
+            for flip in 0..2048 {
+                let fs = slice * 2048 + flip;
+                if classidx[fs] != u16::MAX {
+                    continue;
+                }
+
+                // A new class: this is the first index we meet, so it is the representative
+                let class = rep.len() as u16;
+                let mut cc = slice_cube;
+                cc.eo = CubieCube::set_flip(flip as u16).eo;
+
+                let mut fixed = 0u16;
+                for s in 0..N_SYM_D4H {
+                    // S^-1 * rep * S, so that S * state * S^-1 brings it back to the representative
+                    let conj = syms.cubes[syms.inverse[s] as usize].multiply(&cc).multiply(&syms.cubes[s]);
+                    let fs_new = (conj.get_slice_sorted() as usize) * 2048 + conj.get_flip() as usize;
+                    if fs_new == fs {
+                        fixed |= 1 << s;
+                    }
+                    if classidx[fs_new] == u16::MAX {
+                        classidx[fs_new] = class;
+                        sym[fs_new] = s as u8;
+                    }
+                }
+
+                rep.push(fs as u32);
+                rep_sym.push(fixed);
+            }
+        }
+
+        Self { classidx, sym, rep, rep_sym }
+    }
+}
+
+/// Twist conjugation table: `table[twist * 16 + s]` is the twist of `S * cube * S^-1`.
+/// The twist of the result only depends on the twist of the cube for the 16 D4h symmetries.
+pub fn twist_conjugation_table() -> Vec<u16> {
+    let mut table = vec![0; 2187 * N_SYM_D4H];
+
+    for (twist, row) in table.chunks_mut(N_SYM_D4H).enumerate() {
+        let cc = CubieCube::set_twist(twist as u16);
+        for (s, entry) in row.iter_mut().enumerate() {
+            *entry = cc.conjugate(s).get_twist();
+        }
+    }
+    table
+}
+
+impl CubieCube {
+    /// Conjugates the cube by symmetry `sym`: returns `S * self * S^-1`.
+    /// This is the same state seen after rotating (and maybe mirroring) the whole cube.
+    pub fn conjugate(&self, sym: usize) -> CubieCube {
+        let syms = symmetries();
+        syms.cubes[sym].multiply(self).multiply(&syms.cubes[syms.inverse[sym] as usize])
+    }
+}
+
+impl Turn {
+    /// Relabels the turn under symmetry `sym` (e.g. the LR mirror maps R to L').
+    pub fn conjugate(&self, sym: usize) -> Turn {
+        symmetries().turns[sym][*self as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_symmetry_group() {
+        let syms = symmetries();
+        assert_eq!(syms.cubes[0], CubieCube::SOLVED);
+
+        // All distinct, 24 of them mirrored
+        for i in 0..N_SYM {
+            for j in i + 1..N_SYM {
+                assert_ne!(syms.cubes[i], syms.cubes[j], "Symmetries {} and {} coincide", i, j);
+            }
+        }
+        let mirrored = syms.cubes.iter().filter(|c| c.co[0] >= 3).count();
+        assert_eq!(mirrored, 24);
+
+        // The first 16 keep the U and D faces on the UD axis
+        for s in 0..N_SYM_D4H {
+            assert_eq!(Turn::U.conjugate(s).axis(), 0, "Symmetry {} moves the UD axis", s);
+        }
+    }
+
+    #[test]
+    fn test_conjugation_matches_turn_relabelling() {
+        let mut cube = CubieCube::SOLVED;
+        let sequence = [Turn::R, Turn::U2, Turn::F3, Turn::D, Turn::L2, Turn::B];
+        for m in sequence {
+            cube = cube.multiply(&m.to_cubie());
+        }
+
+        for s in 0..N_SYM {
+            // Conjugating the state equals playing the relabelled sequence
+            let mut relabelled = CubieCube::SOLVED;
+            for m in sequence {
+                relabelled = relabelled.multiply(&m.conjugate(s).to_cubie());
+            }
+            assert_eq!(cube.conjugate(s), relabelled, "Symmetry {} failed", s);
+
+            let s_inv = symmetries().inverse[s] as usize;
+            assert_eq!(cube.conjugate(s).conjugate(s_inv), cube);
+        }
+
+        // The LR mirror swaps R and L and reverses their direction
+        assert_eq!(Turn::R.conjugate(1), Turn::L3);
+    }
+
+    #[test]
+    fn test_flipslice_classes() {
+        let classes = FlipSliceClasses::generate();
+        assert_eq!(classes.rep.len(), N_FLIPSLICE_CLASS);
+
+        // Conjugating any index by its symmetry lands on the representative
+        let mut cube = CubieCube::SOLVED;
+        cube.apply_sequence("R U F' D2 L B'").unwrap();
+        let fs = (cube.get_slice_sorted() as usize) * 2048 + cube.get_flip() as usize;
+        let conj = cube.conjugate(classes.sym[fs] as usize);
+        let fs_rep = (conj.get_slice_sorted() as usize) * 2048 + conj.get_flip() as usize;
+        assert_eq!(fs_rep, classes.rep[classes.classidx[fs] as usize] as usize);
+
+        // The solved state is fixed by every D4h symmetry
+        let solved = 494 * 2048;
+        assert_eq!(classes.rep_sym[classes.classidx[solved] as usize], 0xffff);
+    }
+}
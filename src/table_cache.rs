@@ -0,0 +1,584 @@
+use crate::generation::GenerationConfig;
+use crate::pruning_table::{ ArchivedPruningTables, PruningEncoding, PruningTables };
+use crate::symmetry::N_FLIPSLICE_CLASS;
+
+use std::env;
+use std::fmt;
+use std::fs::{ self, File };
+use std::io::{ self, Read, Write };
+use std::path::{ Path, PathBuf };
+use std::process;
+use std::sync::atomic::{ AtomicU64, Ordering };
+
+use memmap2::Mmap;
+use rkyv::rancor::Error;
+use rkyv::util::AlignedVec;
+
+/// Environment variable that overrides the cache file location.
+pub const CACHE_PATH_ENV: &str = "RUBIKS_CUBE_TABLES";
+
+/// File name of the cache inside the cache directory.
+pub const CACHE_FILE_NAME: &str = "pruning_tables.rkyv";
+
+/// First bytes of every cache file.
+pub const CACHE_MAGIC: [u8; 8] = *b"RCTABLES";
+
+/// Bumped whenever the archived layout of `PruningTables` changes.
+pub const CACHE_FORMAT_VERSION: u32 = 2;
+
+/// The header is padded to 64 bytes so the archive behind it stays aligned.
+pub const HEADER_LEN: usize = 64;
+
+/// Which tables a cache file holds. Only the two-phase set exists so far.
+pub const TABLE_SET_TWO_PHASE: u32 = 1;
+
+/// Where `PruningTables::new` keeps its cache, in order of preference:
+/// 1. the path in `RUBIKS_CUBE_TABLES`,
+/// 2. the per-user cache directory (`$XDG_CACHE_HOME`, `~/.cache`, `~/Library/Caches`
+///    or `%LOCALAPPDATA%`) under `rubiks_cube/`,
+/// 3. `pruning_tables.rkyv` in the working directory.
+pub fn default_cache_path() -> PathBuf {
+    if let Some(path) = env::var_os(CACHE_PATH_ENV).filter(|p| !p.is_empty()) {
+        return PathBuf::from(path);
+    }
+
+    match user_cache_dir() {
+        Some(dir) => dir.join("rubiks_cube").join(CACHE_FILE_NAME),
+        None => PathBuf::from(CACHE_FILE_NAME),
+    }
+}
+
+fn user_cache_dir() -> Option<PathBuf> {
+    let non_empty = |key: &str| env::var_os(key).filter(|v| !v.is_empty()).map(PathBuf::from);
+
+    if cfg!(target_os = "windows") {
+        non_empty("LOCALAPPDATA")
+    } else if cfg!(target_os = "macos") {
+        non_empty("HOME").map(|home| home.join("Library").join("Caches"))
+    } else {
+        non_empty("XDG_CACHE_HOME").or_else(|| non_empty("HOME").map(|home| home.join(".cache")))
+    }
+}
+
+/// The sizes the tables were generated with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TableParams {
+    pub flipslice_classes: u32,
+    pub twist: u32,
+    pub corner_perm: u32,
+    pub ud_edges: u32,
+    pub slice_perm: u32,
+    /// Bits used to store one pruning table entry
+    pub pruning_bits: u32,
+}
+
+impl TableParams {
+    /// The parameters of the tables this version of the crate generates.
+    pub const CURRENT: TableParams = TableParams {
+        flipslice_classes: N_FLIPSLICE_CLASS as u32,
+        twist: 2187,
+        corner_perm: 40320,
+        ud_edges: 40320,
+        slice_perm: 24,
+        pruning_bits: 4,
+    };
+
+    /// The parameters of tables generated with the given pruning encoding.
+    pub const fn for_encoding(encoding: PruningEncoding) -> TableParams {
+        TableParams { pruning_bits: encoding.bits(), ..TableParams::CURRENT }
+    }
+}
+
+/// The fixed-size header in front of the archived tables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheHeader {
+    pub version: u32,
+    pub table_set: u32,
+    pub params: TableParams,
+    pub payload_len: u64,
+    pub checksum: u64,
+}
+
+impl CacheHeader {
+    fn for_payload(payload: &[u8], params: TableParams) -> Self {
+        Self {
+            version: CACHE_FORMAT_VERSION,
+            table_set: TABLE_SET_TWO_PHASE,
+            params,
+            payload_len: payload.len() as u64,
+            checksum: checksum(payload),
+        }
+    }
+
+    fn to_bytes(self) -> [u8; HEADER_LEN] {
+        let mut bytes = [0; HEADER_LEN];
+        let p = self.params;
+        let words = [
+            self.version,
+            self.table_set,
+            p.flipslice_classes,
+            p.twist,
+            p.corner_perm,
+            p.ud_edges,
+            p.slice_perm,
+            p.pruning_bits,
+        ];
+
+        bytes[0..8].copy_from_slice(&CACHE_MAGIC);
+        for (i, w) in words.iter().enumerate() {
+            bytes[8 + i * 4..12 + i * 4].copy_from_slice(&w.to_le_bytes());
+        }
+        bytes[40..48].copy_from_slice(&self.payload_len.to_le_bytes());
+        bytes[48..56].copy_from_slice(&self.checksum.to_le_bytes());
+        bytes
+    }
+
+    /// Parses the header and checks the magic, version, table set and parameters.
+    pub fn parse(bytes: &[u8]) -> Result<Self, TableError> {
+        if bytes.len() < HEADER_LEN || bytes[0..8] != CACHE_MAGIC {
+            return Err(TableError::BadMagic);
+        }
+
+        let word = |i: usize| u32::from_le_bytes(bytes[8 + i * 4..12 + i * 4].try_into().unwrap());
+        let header = Self {
+            version: word(0),
+            table_set: word(1),
+            params: TableParams {
+                flipslice_classes: word(2),
+                twist: word(3),
+                corner_perm: word(4),
+                ud_edges: word(5),
+                slice_perm: word(6),
+                pruning_bits: word(7),
+            },
+            payload_len: u64::from_le_bytes(bytes[40..48].try_into().unwrap()),
+            checksum: u64::from_le_bytes(bytes[48..56].try_into().unwrap()),
+        };
+
+        if header.version != CACHE_FORMAT_VERSION {
+            return Err(TableError::UnsupportedVersion {
+                found: header.version,
+                expected: CACHE_FORMAT_VERSION,
+            });
+        }
+        if header.table_set != TABLE_SET_TWO_PHASE {
+            return Err(TableError::UnknownTableSet(header.table_set));
+        }
+        // Either pruning encoding can be loaded, everything else must match
+        let expected = match header.params.pruning_bits {
+            2 => TableParams::for_encoding(PruningEncoding::Mod3),
+            _ => TableParams::CURRENT,
+        };
+        if header.params != expected {
+            return Err(TableError::ParamsMismatch {
+                found: header.params,
+                expected,
+            });
+        }
+        Ok(header)
+    }
+}
+
+/// 64-bit FNV-1a over little-endian 8-byte words (zero-padded at the end).
+/// Word-at-a-time keeps checking a ~75MB cache cheap next to deserializing it.
+pub fn checksum(bytes: &[u8]) -> u64 {
+    const OFFSET: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+
+    let mut hash = OFFSET;
+    let mut chunks = bytes.chunks_exact(8);
+    for chunk in &mut chunks {
+        hash = (hash ^ u64::from_le_bytes(chunk.try_into().unwrap())).wrapping_mul(PRIME);
+    }
+
+    let rest = chunks.remainder();
+    if !rest.is_empty() {
+        let mut last = [0; 8];
+        last[..rest.len()].copy_from_slice(rest);
+        hash = (hash ^ u64::from_le_bytes(last)).wrapping_mul(PRIME);
+    }
+    hash
+}
+
+/// Why tables could not be loaded from or saved to a cache file.
+#[derive(Debug)]
+pub enum TableError {
+    Io(io::Error),
+    /// The file does not start with `CACHE_MAGIC`, or is shorter than a header.
+    BadMagic,
+    /// The file was written by an incompatible version of the crate.
+    UnsupportedVersion {
+        found: u32,
+        expected: u32,
+    },
+    /// The file holds a set of tables this version does not know.
+    UnknownTableSet(u32),
+    /// The tables were generated with different sizes or encodings.
+    ParamsMismatch {
+        found: TableParams,
+        expected: TableParams,
+    },
+    /// The file is shorter than the header says.
+    Truncated {
+        found: u64,
+        expected: u64,
+    },
+    /// The payload does not hash to the checksum in the header.
+    ChecksumMismatch {
+        found: u64,
+        expected: u64,
+    },
+    /// The payload passed the checksum but is not a valid archive.
+    Deserialize(String),
+    /// The tables could not be serialized for saving.
+    Serialize(String),
+}
+
+impl fmt::Display for TableError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TableError::Io(e) => write!(f, "table cache I/O error: {}", e),
+            TableError::BadMagic => write!(f, "not a pruning table cache file"),
+            TableError::UnsupportedVersion { found, expected } => {
+                write!(f, "table cache format version {} is not supported (expected {})", found, expected)
+            }
+            TableError::UnknownTableSet(set) => write!(f, "unknown table set {}", set),
+            TableError::ParamsMismatch { found, expected } => {
+                write!(f, "table parameters {:?} do not match {:?}", found, expected)
+            }
+            TableError::Truncated { found, expected } => {
+                write!(f, "table cache has {} payload bytes, expected {}", found, expected)
+            }
+            TableError::ChecksumMismatch { found, expected } => {
+                write!(f, "table cache checksum {:#018x} does not match {:#018x}", found, expected)
+            }
+            TableError::Deserialize(e) => write!(f, "table cache could not be deserialized: {}", e),
+            TableError::Serialize(e) => write!(f, "tables could not be serialized: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for TableError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TableError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for TableError {
+    fn from(e: io::Error) -> Self {
+        TableError::Io(e)
+    }
+}
+
+/// What to do when a cache file exists but fails validation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CachePolicy {
+    /// Regenerate the tables and overwrite the file.
+    #[default]
+    Regenerate,
+    /// Return the validation error.
+    Fail,
+}
+
+/// Reads and validates a cache file, returning the archived payload.
+fn read_cache(path: &Path) -> Result<AlignedVec, TableError> {
+    let mut file = File::open(path)?;
+
+    let mut header_bytes = [0; HEADER_LEN];
+    file.read_exact(&mut header_bytes).map_err(|e| {
+        if e.kind() == io::ErrorKind::UnexpectedEof { TableError::BadMagic } else { TableError::Io(e) }
+    })?;
+    let header = CacheHeader::parse(&header_bytes)?;
+
+    // Read into an aligned buffer so the archive can be accessed in place
+    let mut payload = AlignedVec::<16>::new();
+    payload.extend_from_reader(&mut file)?;
+
+    if payload.len() as u64 != header.payload_len {
+        return Err(TableError::Truncated {
+            found: payload.len() as u64,
+            expected: header.payload_len,
+        });
+    }
+    let found = checksum(&payload);
+    if found != header.checksum {
+        return Err(TableError::ChecksumMismatch {
+            found,
+            expected: header.checksum,
+        });
+    }
+    Ok(payload)
+}
+
+/// A cache file mapped into memory and used in place.
+///
+/// Lookups go straight to the archived tables in the page cache, so opening is almost
+/// free and every process mapping the same file shares one copy of it.
+pub struct MappedTables {
+    mmap: Mmap,
+}
+
+impl MappedTables {
+    /// Maps a cache file written by `PruningTables::load_or_generate`.
+    ///
+    /// The header and the archive structure are validated, but the payload checksum is
+    /// not, since that would read every page; call `verify_checksum` for that.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, TableError> {
+        let file = File::open(path)?;
+        // Safety: the cache file must not be modified while it is mapped.
+        // Writers replace it with a fresh file rather than editing it in place.
+        let mmap = unsafe { Mmap::map(&file)? };
+
+        let header = CacheHeader::parse(&mmap)?;
+        let payload = &mmap[HEADER_LEN..];
+        if payload.len() as u64 != header.payload_len {
+            return Err(TableError::Truncated {
+                found: payload.len() as u64,
+                expected: header.payload_len,
+            });
+        }
+        rkyv::access::<ArchivedPruningTables, Error>(payload).map_err(|e| TableError::Deserialize(e.to_string()))?;
+
+        Ok(Self { mmap })
+    }
+
+    /// Hashes the whole payload and compares it with the header checksum.
+    pub fn verify_checksum(&self) -> Result<(), TableError> {
+        let header = CacheHeader::parse(&self.mmap)?;
+        let found = checksum(&self.mmap[HEADER_LEN..]);
+        if found != header.checksum {
+            return Err(TableError::ChecksumMismatch {
+                found,
+                expected: header.checksum,
+            });
+        }
+        Ok(())
+    }
+
+    /// The archived tables, usable directly with `Solver::new`.
+    pub fn tables(&self) -> &ArchivedPruningTables {
+        // Safety: the archive was validated in `open`
+        unsafe { rkyv::access_unchecked::<ArchivedPruningTables>(&self.mmap[HEADER_LEN..]) }
+    }
+}
+
+// A temp file next to `path` that no other process or concurrent `save` will pick,
+// so only complete files are ever renamed into place
+fn temp_path_for(path: &Path) -> PathBuf {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}.{}.tmp", process::id(), n));
+    path.with_file_name(name)
+}
+
+impl PruningTables {
+    /// Reads tables written by `save`, validating the header and checksum.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, TableError> {
+        let payload = read_cache(path.as_ref())?;
+        rkyv::from_bytes::<PruningTables, Error>(&payload).map_err(|e| TableError::Deserialize(e.to_string()))
+    }
+
+    /// Writes the tables to `path` with a versioned, checksummed header.
+    ///
+    /// The file is written next to `path` and renamed over it, so processes that
+    /// have the old file mapped keep seeing a consistent copy.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), TableError> {
+        let path = path.as_ref();
+        let payload = rkyv::to_bytes::<Error>(self).map_err(|e| TableError::Serialize(e.to_string()))?;
+        let header = CacheHeader::for_payload(&payload, TableParams::for_encoding(self.encoding()));
+
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+
+        let tmp_path = temp_path_for(path);
+        let written = File::create(&tmp_path).and_then(|mut file| {
+            file.write_all(&header.to_bytes())?;
+            file.write_all(&payload)?;
+            file.sync_all()
+        });
+        if let Err(e) = written.and_then(|_| fs::rename(&tmp_path, path)) {
+            let _ = fs::remove_file(&tmp_path);
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    /// Loads the tables from `path`, or generates them and tries to cache them there.
+    /// An unusable cache file is regenerated.
+    ///
+    /// The cache is only an optimisation here, so failing to write it is not reported.
+    /// Use `load`, `generate` and `save` directly to see every failure.
+    pub fn load_or_generate(path: impl AsRef<Path>) -> Self {
+        match Self::load_or_generate_with_config(path, CachePolicy::Regenerate, &GenerationConfig::default()) {
+            Ok((tables, _)) => tables,
+            Err(_) => unreachable!("CachePolicy::Regenerate never returns an error"),
+        }
+    }
+
+    /// Like `load_or_generate`, but lets the caller choose what happens when the
+    /// cache exists but is invalid (wrong magic, version, parameters or checksum).
+    ///
+    /// Freshly generated tables are returned even if writing the cache fails,
+    /// together with the write error so the caller can report it.
+    pub fn load_or_generate_with(
+        path: impl AsRef<Path>,
+        policy: CachePolicy
+    ) -> Result<(Self, Option<TableError>), TableError> {
+        Self::load_or_generate_with_config(path, policy, &GenerationConfig::default())
+    }
+
+    /// Like `load_or_generate_with`, but generates missing tables with `config`, so the
+    /// first run can report progress or use a different thread count or encoding.
+    /// A valid cache is loaded as it is, whatever its encoding.
+    pub fn load_or_generate_with_config(
+        path: impl AsRef<Path>,
+        policy: CachePolicy,
+        config: &GenerationConfig
+    ) -> Result<(Self, Option<TableError>), TableError> {
+        let path = path.as_ref();
+
+        match Self::load(path) {
+            Ok(tables) => {
+                return Ok((tables, None));
+            }
+            // No cache yet, nothing to report
+            Err(TableError::Io(e)) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => {
+                if policy == CachePolicy::Fail {
+                    return Err(e);
+                }
+            }
+        }
+
+        let tables = Self::generate_with(config);
+        let save_error = tables.save(path).err();
+        Ok((tables, save_error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_round_trip() {
+        let payload = b"not really an archive";
+        let header = CacheHeader::for_payload(payload, TableParams::CURRENT);
+        assert_eq!(CacheHeader::parse(&header.to_bytes()).unwrap(), header);
+
+        let mut bytes = header.to_bytes();
+        bytes[0] = b'X';
+        assert!(matches!(CacheHeader::parse(&bytes), Err(TableError::BadMagic)));
+
+        let mut bytes = header.to_bytes();
+        bytes[8] = 99;
+        assert!(
+            matches!(CacheHeader::parse(&bytes), Err(TableError::UnsupportedVersion { found: 99, .. }))
+        );
+
+        let mut bytes = header.to_bytes();
+        bytes[36] = 3;
+        assert!(matches!(CacheHeader::parse(&bytes), Err(TableError::ParamsMismatch { .. })));
+
+        let mod3 = CacheHeader::for_payload(payload, TableParams::for_encoding(PruningEncoding::Mod3));
+        assert_eq!(CacheHeader::parse(&mod3.to_bytes()).unwrap(), mod3);
+    }
+
+    #[test]
+    fn test_corrupted_cache_is_rejected() {
+        let dir = std::env::temp_dir().join(format!("rubiks_cube_cache_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(CACHE_FILE_NAME);
+
+        let payload = vec![7u8; 1000];
+        let header = CacheHeader::for_payload(&payload, TableParams::CURRENT);
+        let mut bytes = header.to_bytes().to_vec();
+        bytes.extend_from_slice(&payload);
+        bytes[HEADER_LEN + 10] ^= 1;
+        fs::write(&path, &bytes).unwrap();
+
+        let result = PruningTables::load_or_generate_with(&path, CachePolicy::Fail);
+        assert!(matches!(result, Err(TableError::ChecksumMismatch { .. })));
+
+        bytes.truncate(HEADER_LEN + 500);
+        fs::write(&path, &bytes).unwrap();
+        let result = PruningTables::load_or_generate_with(&path, CachePolicy::Fail);
+        assert!(matches!(result, Err(TableError::Truncated { found: 500, expected: 1000 })));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_existing_cache_is_not_regenerated() {
+        use std::sync::atomic::AtomicUsize;
+        use std::sync::Arc;
+
+        // Makes sure the shared cache file exists
+        let shared = crate::pruning_table::test_tables();
+
+        let reports = Arc::new(AtomicUsize::new(0));
+        let counter = reports.clone();
+        let config = GenerationConfig::new().on_progress(move |_| {
+            counter.fetch_add(1, Ordering::Relaxed);
+        });
+        let path = crate::pruning_table::TEST_TABLES_PATH;
+        let (tables, save_error) = PruningTables::load_or_generate_with_config(path, CachePolicy::Fail, &config).unwrap();
+        assert!(save_error.is_none());
+        assert_eq!(reports.load(Ordering::Relaxed), 0);
+        assert!(tables.cp_move.data == shared.cp_move.data);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = std::env::temp_dir().join(format!("rubiks_cube_save_test_{}", std::process::id()));
+        let path = dir.join("nested").join(CACHE_FILE_NAME);
+
+        let tables = crate::pruning_table::test_tables();
+        tables.save(&path).unwrap();
+        let loaded = PruningTables::load(&path).unwrap();
+        assert!(loaded.cp_move.data == tables.cp_move.data);
+        assert_eq!(fs::read_dir(path.parent().unwrap()).unwrap().count(), 1, "temp file left behind");
+        assert_ne!(temp_path_for(&path), temp_path_for(&path));
+
+        // A directory where the file should go cannot be written over
+        let blocked = dir.join("blocked");
+        fs::create_dir_all(blocked.join("inside")).unwrap();
+        assert!(matches!(tables.save(&blocked), Err(TableError::Io(_))));
+        assert!(matches!(PruningTables::load(dir.join("missing")), Err(TableError::Io(_))));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_mapped_tables_match_owned() {
+        use crate::coord_cube::CoordCube;
+        use crate::cubie_cube::CubieCube;
+        use crate::pruning_table::{ TableLookup, test_tables };
+        use crate::solver::Solver;
+
+        let owned = test_tables();
+        let mapped = MappedTables::open(crate::pruning_table::TEST_TABLES_PATH).unwrap();
+        mapped.verify_checksum().unwrap();
+        let archived = mapped.tables();
+
+        let mut cube = CubieCube::SOLVED;
+        for m in "R U F' D2 L B' R2 U' F".split_whitespace() {
+            cube.apply_sequence(m).unwrap();
+            let c = CoordCube::from_cubie(&cube);
+            assert_eq!(c.phase1_depth(archived).unwrap(), c.phase1_depth(owned).unwrap());
+            assert_eq!(
+                archived.phase2_depth(c.corner as usize, c.ud_edges as usize, c.slice_perm as usize).unwrap(),
+                owned.phase2_depth(c.corner as usize, c.ud_edges as usize, c.slice_perm as usize).unwrap()
+            );
+        }
+
+        let solution = Solver::new(archived).solve(&cube).unwrap();
+        cube.apply_moves(&solution.moves);
+        assert_eq!(cube, CubieCube::SOLVED);
+    }
+}
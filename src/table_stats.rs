@@ -0,0 +1,393 @@
+use crate::generation::SearchSpace;
+use crate::pruning_table::{
+    ArchivedPruningArray,
+    ArchivedPruningTables,
+    FlipSliceTwistSpace,
+    PairSpace,
+    PruningArray,
+    PruningEncoding,
+    PruningTables,
+};
+use crate::rng::rng_from_seed;
+use crate::turn::Turn;
+
+use std::fmt;
+
+use rand::RngExt;
+use rand::seq::IndexedRandom;
+
+/// Entries checked against their neighbours by `verify`, per table.
+const NEIGHBOUR_SAMPLES: usize = 100_000;
+
+/// States re-derived by an independent search in `verify`, per table.
+const SEARCH_SAMPLES: usize = 16;
+
+/// Deepest distance the independent search re-derives. It does not prune, so keep this small.
+const SEARCH_DEPTH: u8 = 5;
+
+/// Entry reads shared by owned and archived pruning tables.
+trait PruningEntries: Sync {
+    fn encoding(&self) -> PruningEncoding;
+    fn length(&self) -> usize;
+    fn get(&self, index: usize) -> u8;
+}
+
+macro_rules! impl_pruning_entries {
+    ($array:ty) => {
+        impl PruningEntries for $array {
+            fn encoding(&self) -> PruningEncoding {
+                <$array>::encoding(self)
+            }
+
+            fn length(&self) -> usize {
+                <$array>::length(self)
+            }
+
+            #[inline(always)]
+            fn get(&self, index: usize) -> u8 {
+                <$array>::get(self, index)
+            }
+        }
+    };
+}
+
+impl_pruning_entries!(PruningArray);
+impl_pruning_entries!(ArchivedPruningArray);
+
+/// How many entries of one pruning table sit at each distance.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DepthHistogram {
+    pub encoding: PruningEncoding,
+    /// `counts[d]` is the number of entries at distance `d`.
+    /// `Mod3` tables only know distances mod 3, so there `counts` has the three residues.
+    pub counts: Vec<usize>,
+    /// Entries left at 0xF, either unreachable or beyond the generation depth limit.
+    pub unvisited: usize,
+}
+
+impl DepthHistogram {
+    fn of(table: &impl PruningEntries) -> Self {
+        let mut all = [0usize; 16];
+        for i in 0..table.length() {
+            all[table.get(i) as usize] += 1;
+        }
+
+        let deepest = (0..15).rev().find(|&d| all[d] != 0).map_or(0, |d| d + 1);
+        Self {
+            encoding: table.encoding(),
+            counts: all[..deepest].to_vec(),
+            unvisited: all[15],
+        }
+    }
+
+    /// Largest distance stored in the table, if the encoding keeps exact distances.
+    pub fn max_depth(&self) -> Option<u8> {
+        if self.encoding != PruningEncoding::Nibble {
+            return None;
+        }
+        self.counts.len().checked_sub(1).map(|d| d as u8)
+    }
+
+    /// Total number of entries, unvisited ones included.
+    pub fn total(&self) -> usize {
+        self.counts.iter().sum::<usize>() + self.unvisited
+    }
+}
+
+/// Distance distributions of every pruning table.
+///
+/// The Phase 1 table is symmetry reduced, so its counts are per (flip-slice class, twist)
+/// entry rather than per cube state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableStats {
+    pub flipslice_twist_pruning: DepthHistogram,
+    pub corner_slice_pruning: DepthHistogram,
+    pub ud_edge_slice_pruning: DepthHistogram,
+}
+
+/// A pruning table entry that cannot be a BFS distance.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyError {
+    /// The solved state is not at distance 0.
+    SolvedNotZero {
+        table: &'static str,
+        found: u8,
+    },
+    /// An entry and one of its neighbours are more than one move apart.
+    NeighbourMismatch {
+        table: &'static str,
+        index: usize,
+        depth: u8,
+        neighbour: usize,
+        neighbour_depth: u8,
+    },
+    /// An entry has no neighbour one move closer to solved.
+    /// `depth` is the stored value, so the distance mod 3 for `Mod3` tables.
+    NoPredecessor {
+        table: &'static str,
+        index: usize,
+        depth: u8,
+    },
+    /// A `Mod3` entry that cannot be one move away from the distance it was reached from.
+    BadStep {
+        table: &'static str,
+        index: usize,
+        parent: u8,
+        value: u8,
+    },
+    /// A stored value outside the range of the encoding.
+    InvalidValue {
+        table: &'static str,
+        index: usize,
+        value: u8,
+    },
+    /// An independent search found a different distance than the table stores.
+    WrongDistance {
+        table: &'static str,
+        index: usize,
+        stored: u8,
+        searched: u8,
+    },
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VerifyError::SolvedNotZero { table, found } => {
+                write!(f, "{}: solved state has distance {}, expected 0", table, found)
+            }
+            VerifyError::NeighbourMismatch { table, index, depth, neighbour, neighbour_depth } => {
+                write!(
+                    f,
+                    "{}: entry {} at distance {} has neighbour {} at distance {}",
+                    table,
+                    index,
+                    depth,
+                    neighbour,
+                    neighbour_depth
+                )
+            }
+            VerifyError::NoPredecessor { table, index, depth } => {
+                write!(f, "{}: entry {} storing {} has no neighbour one move closer to solved", table, index, depth)
+            }
+            VerifyError::BadStep { table, index, parent, value } => {
+                write!(f, "{}: entry {} storing {} cannot be one move from distance {}", table, index, value, parent)
+            }
+            VerifyError::InvalidValue { table, index, value } => {
+                write!(f, "{}: entry {} holds {}, which is not a valid value", table, index, value)
+            }
+            VerifyError::WrongDistance { table, index, stored, searched } => {
+                write!(f, "{}: entry {} stores distance {} but is {} moves from solved", table, index, stored, searched)
+            }
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+// Owned, mapped and embedded tables are all checked the same way
+macro_rules! impl_table_checks {
+    ($tables:ty) => {
+        impl $tables {
+            /// Counts the entries at each distance in every pruning table.
+            pub fn stats(&self) -> TableStats {
+                TableStats {
+                    flipslice_twist_pruning: DepthHistogram::of(&self.flipslice_twist_pruning),
+                    corner_slice_pruning: DepthHistogram::of(&self.corner_slice_pruning),
+                    ud_edge_slice_pruning: DepthHistogram::of(&self.ud_edge_slice_pruning),
+                }
+            }
+
+            /// Spot-checks the pruning tables for corruption.
+            ///
+            /// The solved state must be at 0, sampled entries must be within one move of all their
+            /// neighbours and one move further than at least one of them, and a few shallow states
+            /// are re-derived with a plain search over the move tables.
+            /// The samples are fixed, so the result is reproducible.
+            pub fn verify(&self) -> Result<(), VerifyError> {
+                let phase1 = FlipSliceTwistSpace::new(
+                    &self.twist_move,
+                    &self.flip_move,
+                    &self.slice_move,
+                    &self.flipslice_classes,
+                    &self.twist_conj[..]
+                );
+                verify_table(&phase1, &self.flipslice_twist_pruning, phase1.solved_index(), "flipslice_twist_pruning")?;
+
+                let corner_slice = PairSpace::new(&self.cp_move, &self.ep_slice_move, &Turn::PHASE2_MOVES);
+                verify_table(&corner_slice, &self.corner_slice_pruning, 0, "corner_slice_pruning")?;
+
+                let ud_edge_slice = PairSpace::new(&self.ud_edge_move, &self.ep_slice_move, &Turn::PHASE2_MOVES);
+                verify_table(&ud_edge_slice, &self.ud_edge_slice_pruning, 0, "ud_edge_slice_pruning")
+            }
+        }
+    };
+}
+
+impl_table_checks!(PruningTables);
+impl_table_checks!(ArchivedPruningTables);
+
+fn verify_table(space: &impl SearchSpace, table: &impl PruningEntries, solved: usize, name: &'static str) -> Result<(), VerifyError> {
+    let found = table.get(solved);
+    if found != 0 {
+        return Err(VerifyError::SolvedNotZero { table: name, found });
+    }
+
+    let mut rng = rng_from_seed(0x5eed);
+
+    for _ in 0..NEIGHBOUR_SAMPLES {
+        let index = rng.random_range(0..table.length());
+        check_neighbours(space, table, index, solved, name)?;
+    }
+
+    // Walk a few random moves away from solved and search back
+    for _ in 0..SEARCH_SAMPLES {
+        let mut index = solved;
+        for _ in 0..rng.random_range(0..=SEARCH_DEPTH) {
+            let mut neighbours = Vec::new();
+            space.for_each_neighbour(index, |n| neighbours.push(n));
+            index = *neighbours.choose(&mut rng).unwrap();
+        }
+
+        let stored = table.get(index);
+        let searched = (0..=SEARCH_DEPTH)
+            .find(|&bound| reaches(space, index, solved, bound))
+            .expect("a random walk of SEARCH_DEPTH moves is undone within SEARCH_DEPTH moves");
+        let expected = match table.encoding() {
+            PruningEncoding::Nibble => searched,
+            PruningEncoding::Mod3 => searched % 3,
+        };
+        if stored != expected {
+            return Err(VerifyError::WrongDistance { table: name, index, stored, searched });
+        }
+    }
+    Ok(())
+}
+
+// 0xF stands for "further than the table goes", which is at least 14 when it
+// was generated with a depth limit.
+fn check_neighbours(
+    space: &impl SearchSpace,
+    table: &impl PruningEntries,
+    index: usize,
+    solved: usize,
+    name: &'static str
+) -> Result<(), VerifyError> {
+    if table.encoding() == PruningEncoding::Mod3 {
+        return check_mod3_neighbours(space, table, index, solved, name);
+    }
+
+    let depth = table.get(index);
+    let mut mismatch = None;
+    let mut has_predecessor = depth == 0;
+
+    space.for_each_neighbour(index, |n| {
+        let d = table.get(n);
+        let close = match (depth, d) {
+            (0xf, 0xf) => true,
+            (0xf, d) | (d, 0xf) => d >= 14,
+            (a, b) => a.abs_diff(b) <= 1,
+        };
+        if !close && mismatch.is_none() {
+            mismatch = Some((n, d));
+        }
+        if depth != 0xf && d + 1 == depth {
+            has_predecessor = true;
+        }
+    });
+
+    if let Some((neighbour, neighbour_depth)) = mismatch {
+        return Err(VerifyError::NeighbourMismatch { table: name, index, depth, neighbour, neighbour_depth });
+    }
+    if !has_predecessor && depth != 0xf {
+        return Err(VerifyError::NoPredecessor { table: name, index, depth });
+    }
+    Ok(())
+}
+
+// Neighbours can be at any residue, so the only local check is that every entry
+// but the goal has a neighbour one closer
+fn check_mod3_neighbours(
+    space: &impl SearchSpace,
+    table: &impl PruningEntries,
+    index: usize,
+    solved: usize,
+    name: &'static str
+) -> Result<(), VerifyError> {
+    let residue = table.get(index);
+    if residue > 2 {
+        return Err(VerifyError::InvalidValue { table: name, index, value: residue });
+    }
+    let closer = (residue + 2) % 3;
+    if index != solved && !space.any_neighbour(index, |n| table.get(n) == closer) {
+        return Err(VerifyError::NoPredecessor { table: name, index, depth: residue });
+    }
+    Ok(())
+}
+
+// Depth-limited search without any pruning, so it does not trust the table under test
+fn reaches(space: &impl SearchSpace, index: usize, goal: usize, bound: u8) -> bool {
+    if bound == 0 {
+        return index == goal;
+    }
+    space.any_neighbour(index, |n| reaches(space, n, goal, bound - 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pruning_table::{ test_mod3_tables, test_tables, NibbleArray };
+    use crate::table_cache::MappedTables;
+
+    #[test]
+    fn test_stats_match_known_distribution() {
+        let stats = test_tables().stats();
+
+        let phase1 = &stats.flipslice_twist_pruning;
+        assert_eq!(
+            phase1.counts,
+            [1, 1, 5, 44, 487, 5841, 68364, 776568, 7950748, 52098876, 76236234, 3771112, 129]
+        );
+        assert_eq!(phase1.unvisited, 0);
+        assert_eq!(phase1.total(), 140_908_410);
+
+        assert_eq!(stats.corner_slice_pruning.total(), 40320 * 24);
+        assert_eq!(stats.corner_slice_pruning.counts[0], 1);
+        assert_eq!(stats.ud_edge_slice_pruning.total(), 40320 * 24);
+    }
+
+    #[test]
+    fn test_verify_detects_corruption() {
+        let tables = test_tables();
+        assert_eq!(tables.verify(), Ok(()));
+
+        let space = PairSpace::new(&tables.cp_move, &tables.ep_slice_move, &Turn::PHASE2_MOVES);
+        let PruningArray::Nibble(distances) = &tables.corner_slice_pruning else { panic!("test tables are not nibble encoded") };
+        let mut corrupted = distances.clone();
+        corrupted.set(0, 3);
+        let corrupted = PruningArray::Nibble(corrupted);
+        assert!(matches!(verify_table(&space, &corrupted, 0, "test"), Err(VerifyError::SolvedNotZero { .. })));
+
+        // Shifting every entry keeps most neighbours consistent with each other
+        let mut shifted = NibbleArray::new(distances.length, 0);
+        for i in 1..shifted.length {
+            shifted.set(i, (distances.get(i) + 1).min(0xe));
+        }
+        assert!(verify_table(&space, &PruningArray::Nibble(shifted.clone()), 0, "test").is_err());
+
+        // Mod 3 tables are checked too
+        assert_eq!(test_mod3_tables().verify(), Ok(()));
+        let shifted = PruningArray::encode(shifted, PruningEncoding::Mod3).unwrap();
+        assert!(verify_table(&space, &shifted, 0, "test").is_err());
+    }
+
+    #[test]
+    fn test_verify_mapped_tables() {
+        // Loading the owned tables first makes sure the cache file exists
+        let stats = test_tables().stats();
+
+        let mapped = MappedTables::open(crate::pruning_table::TEST_TABLES_PATH).unwrap();
+        assert_eq!(mapped.tables().verify(), Ok(()));
+        assert_eq!(mapped.tables().stats(), stats);
+    }
+}
@@ -142,6 +142,38 @@ impl Turn {
         }
     }
 
+    /// Returns the move that undoes this one (R <-> R', R2 <-> R2)
+    pub fn inverse(&self) -> Turn {
+        match self {
+            Turn::U => Turn::U3,
+            Turn::U2 => Turn::U2,
+            Turn::U3 => Turn::U,
+            Turn::R => Turn::R3,
+            Turn::R2 => Turn::R2,
+            Turn::R3 => Turn::R,
+            Turn::F => Turn::F3,
+            Turn::F2 => Turn::F2,
+            Turn::F3 => Turn::F,
+            Turn::D => Turn::D3,
+            Turn::D2 => Turn::D2,
+            Turn::D3 => Turn::D,
+            Turn::L => Turn::L3,
+            Turn::L2 => Turn::L2,
+            Turn::L3 => Turn::L,
+            Turn::B => Turn::B3,
+            Turn::B2 => Turn::B2,
+            Turn::B3 => Turn::B,
+        }
+    }
+
+    /// Length of the move in Quarter-Turn Metric (half turns count twice)
+    pub fn quarter_turns(&self) -> u8 {
+        match self {
+            Turn::U2 | Turn::R2 | Turn::F2 | Turn::D2 | Turn::L2 | Turn::B2 => 2,
+            _ => 1,
+        }
+    }
+
     /// Returns the "axis" of the move (0=UD, 1=LR, 2=FB)
     pub fn axis(&self) -> u8 {
         match self {
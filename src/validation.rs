@@ -0,0 +1,239 @@
+use crate::cubie_cube::{ Corner, CubieCube, Edge };
+
+use std::fmt;
+
+/// Direction of a corner twist, as seen looking at the corner from outside the cube.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TwistDirection {
+    Clockwise,
+    CounterClockwise,
+}
+
+/// The first rule a `CubieCube` breaks on its way to being a reachable state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    /// `cp` holds a value that is not a corner (>= 8).
+    InvalidCorner {
+        position: Corner,
+        value: u8,
+    },
+    /// `ep` holds a value that is not an edge (>= 12).
+    InvalidEdge {
+        position: Edge,
+        value: u8,
+    },
+    /// `co` holds a value that is not an orientation (>= 3).
+    InvalidCornerOrientation {
+        position: Corner,
+        value: u8,
+    },
+    /// `eo` holds a value that is not an orientation (>= 2).
+    InvalidEdgeOrientation {
+        position: Edge,
+        value: u8,
+    },
+    /// The same corner piece sits in two positions (so another one is missing).
+    DuplicateCorner {
+        piece: Corner,
+        positions: [Corner; 2],
+    },
+    /// The same edge piece sits in two positions (so another one is missing).
+    DuplicateEdge {
+        piece: Edge,
+        positions: [Edge; 2],
+    },
+    /// Corner orientations do not sum to 0 mod 3: one corner is twisted in place.
+    /// `suspect` is set when exactly one corner carries that twist.
+    TwistedCorner {
+        direction: TwistDirection,
+        suspect: Option<Corner>,
+    },
+    /// Edge orientations do not sum to 0 mod 2: one edge is flipped in place.
+    /// `suspect` is set when exactly one edge is flipped.
+    FlippedEdge {
+        suspect: Option<Edge>,
+    },
+    /// Corner and edge permutation parities differ: two pieces are swapped.
+    ParityMismatch,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ValidationError::InvalidCorner { position, value } => {
+                write!(f, "corner at {:?} has invalid piece index {}", position, value)
+            }
+            ValidationError::InvalidEdge { position, value } => {
+                write!(f, "edge at {:?} has invalid piece index {}", position, value)
+            }
+            ValidationError::InvalidCornerOrientation { position, value } => {
+                write!(f, "corner at {:?} has invalid orientation {}", position, value)
+            }
+            ValidationError::InvalidEdgeOrientation { position, value } => {
+                write!(f, "edge at {:?} has invalid orientation {}", position, value)
+            }
+            ValidationError::DuplicateCorner { piece, positions } => {
+                write!(f, "corner {:?} appears at both {:?} and {:?}", piece, positions[0], positions[1])
+            }
+            ValidationError::DuplicateEdge { piece, positions } => {
+                write!(f, "edge {:?} appears at both {:?} and {:?}", piece, positions[0], positions[1])
+            }
+            ValidationError::TwistedCorner { direction, suspect } => {
+                let dir = match direction {
+                    TwistDirection::Clockwise => "clockwise",
+                    TwistDirection::CounterClockwise => "counter-clockwise",
+                };
+                match suspect {
+                    Some(c) => write!(f, "corner at {:?} twisted {}", c, dir),
+                    None => write!(f, "one corner is twisted {}", dir),
+                }
+            }
+            ValidationError::FlippedEdge { suspect } => {
+                match suspect {
+                    Some(e) => write!(f, "edge at {:?} flipped", e),
+                    None => write!(f, "one edge is flipped"),
+                }
+            }
+            ValidationError::ParityMismatch => write!(f, "two edges (or two corners) swapped"),
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+impl CubieCube {
+    /// Checks that the cube is reachable from the solved state by face turns.
+    /// Rules are checked in order: piece ranges, orientation ranges, duplicates,
+    /// corner twist, edge flip and finally permutation parity.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        for (i, &piece) in self.cp.iter().enumerate() {
+            if piece >= 8 {
+                return Err(ValidationError::InvalidCorner { position: Corner::ALL[i], value: piece });
+            }
+        }
+        for (i, &piece) in self.ep.iter().enumerate() {
+            if piece >= 12 {
+                return Err(ValidationError::InvalidEdge { position: Edge::ALL[i], value: piece });
+            }
+        }
+        for (i, &ori) in self.co.iter().enumerate() {
+            if ori >= 3 {
+                return Err(ValidationError::InvalidCornerOrientation {
+                    position: Corner::ALL[i],
+                    value: ori,
+                });
+            }
+        }
+        for (i, &ori) in self.eo.iter().enumerate() {
+            if ori >= 2 {
+                return Err(ValidationError::InvalidEdgeOrientation {
+                    position: Edge::ALL[i],
+                    value: ori,
+                });
+            }
+        }
+
+        // Remember where each piece was first seen to report both positions of a duplicate
+        let mut corner_seen: [Option<usize>; 8] = [None; 8];
+        for (i, &piece) in self.cp.iter().enumerate() {
+            if let Some(first) = corner_seen[piece as usize] {
+                return Err(ValidationError::DuplicateCorner {
+                    piece: Corner::ALL[piece as usize],
+                    positions: [Corner::ALL[first], Corner::ALL[i]],
+                });
+            }
+            corner_seen[piece as usize] = Some(i);
+        }
+
+        let mut edge_seen: [Option<usize>; 12] = [None; 12];
+        for (i, &piece) in self.ep.iter().enumerate() {
+            if let Some(first) = edge_seen[piece as usize] {
+                return Err(ValidationError::DuplicateEdge {
+                    piece: Edge::ALL[piece as usize],
+                    positions: [Edge::ALL[first], Edge::ALL[i]],
+                });
+            }
+            edge_seen[piece as usize] = Some(i);
+        }
+
+        let twist = self.co.iter().map(|&o| o as u32).sum::<u32>() % 3;
+        if twist != 0 {
+            // A corner with orientation 1 has been turned clockwise in place
+            let direction = if twist == 1 {
+                TwistDirection::Clockwise
+            } else {
+                TwistDirection::CounterClockwise
+            };
+            return Err(ValidationError::TwistedCorner {
+                direction,
+                suspect: single_position(&self.co, twist as u8).map(|i| Corner::ALL[i]),
+            });
+        }
+
+        let flip = self.eo.iter().map(|&o| o as u32).sum::<u32>() % 2;
+        if flip != 0 {
+            return Err(ValidationError::FlippedEdge {
+                suspect: single_position(&self.eo, 1).map(|i| Edge::ALL[i]),
+            });
+        }
+
+        if self.corner_parity() != self.edge_parity() {
+            return Err(ValidationError::ParityMismatch);
+        }
+
+        Ok(())
+    }
+}
+
+// Index of the only orientation equal to `value` with every other one zero
+fn single_position(orientations: &[u8], value: u8) -> Option<usize> {
+    let mut nonzero = orientations
+        .iter()
+        .enumerate()
+        .filter(|(_, o)| **o != 0);
+    match (nonzero.next(), nonzero.next()) {
+        (Some((i, &o)), None) if o == value => Some(i),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let mut cube = CubieCube::SOLVED;
+        assert_eq!(cube.validate(), Ok(()));
+        cube.apply_sequence("R U R' U' F2 D L' B").unwrap();
+        assert_eq!(cube.validate(), Ok(()));
+
+        let mut twisted = CubieCube::SOLVED;
+        twisted.co[4] = 1;
+        assert_eq!(
+            twisted.validate(),
+            Err(ValidationError::TwistedCorner {
+                direction: TwistDirection::Clockwise,
+                suspect: Some(Corner::DFR),
+            })
+        );
+
+        let mut flipped = CubieCube::SOLVED;
+        flipped.eo[1] = 1;
+        assert_eq!(flipped.validate(), Err(ValidationError::FlippedEdge { suspect: Some(Edge::UF) }));
+
+        let mut swapped = CubieCube::SOLVED;
+        swapped.ep.swap(0, 1);
+        assert_eq!(swapped.validate(), Err(ValidationError::ParityMismatch));
+
+        let mut duplicate = CubieCube::SOLVED;
+        duplicate.cp[7] = 0;
+        assert_eq!(
+            duplicate.validate(),
+            Err(ValidationError::DuplicateCorner {
+                piece: Corner::URF,
+                positions: [Corner::URF, Corner::DRB],
+            })
+        );
+    }
+}